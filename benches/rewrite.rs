@@ -0,0 +1,102 @@
+//! Rough timings for the hot paths of rewriting.
+//!
+//! Run with `cargo bench`. This uses a plain `main` instead of a benchmarking
+//! harness so that it works on stable without any extra dependencies.
+
+use std::{hint::black_box, time::Instant};
+use trexp::{Children, Clean, Dirty, Rewrite, TreeRewrite, TreeWalk};
+
+enum Expr {
+    Num(u64),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+}
+use Expr::*;
+
+impl Default for Expr {
+    fn default() -> Self {
+        Num(0)
+    }
+}
+
+impl Children for Expr {
+    fn children(&self) -> impl Iterator<Item = &Self> {
+        match self {
+            Num(_) => [None, None],
+            Neg(e) => [Some(&**e), None],
+            Add(a, b) => [Some(&**a), Some(&**b)],
+        }
+        .into_iter()
+        .flatten()
+    }
+}
+
+impl TreeWalk<Rewrite<Self>> for Expr {
+    fn each_branch(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self> {
+        match self {
+            Num(_) => Clean(self),
+            Neg(e) => f(*e).map(|e| Neg(Box::new(e))),
+            Add(a, b) => {
+                f(*a).bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b))))
+            }
+        }
+    }
+}
+
+fn balanced(depth: u32) -> Expr {
+    if depth == 0 {
+        Num(1)
+    } else {
+        Add(Box::new(balanced(depth - 1)), Box::new(balanced(depth - 1)))
+    }
+}
+
+fn chain(depth: u32) -> Expr {
+    (0..depth).fold(Num(1), |e, _| Neg(Box::new(e)))
+}
+
+fn fold_constants(expr: Expr) -> Rewrite<Expr> {
+    match expr {
+        Add(a, b) => match (*a, *b) {
+            (Num(a), Num(b)) => Dirty(Num(a + b)),
+            (a, b) => Clean(Add(Box::new(a), Box::new(b))),
+        },
+        _ => Clean(expr),
+    }
+}
+
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let per_iteration = start.elapsed() / iterations;
+    println!("{name:<33} {per_iteration:>12?} per iteration");
+}
+
+fn main() {
+    bench("repeat/collatz", 1000, || {
+        for n in 1..1000_u64 {
+            black_box(Rewrite::repeat(black_box(n), |n| match n {
+                1 => Clean(1),
+                n if n % 2 == 0 => Dirty(n / 2),
+                n => Dirty(3 * n + 1),
+            }));
+        }
+    });
+    bench("bottom_up/fold_constants", 100, || {
+        black_box(balanced(black_box(12)).bottom_up(fold_constants));
+    });
+    bench("bottom_up/chain", 100, || {
+        black_box(chain(black_box(10_000)).bottom_up(fold_constants));
+    });
+    bench("bottom_up_iterative_spine/chain", 100, || {
+        black_box(
+            chain(black_box(10_000)).bottom_up_iterative_spine(fold_constants),
+        );
+    });
+}
@@ -0,0 +1,93 @@
+use crate::Dirtiness;
+use alloc::vec::Vec;
+
+/// Trait for arenas that store the nodes of tree-like structures, with
+/// branches referred to by ids of type `Id` rather than owned directly.
+///
+/// This is implemented by the arena itself rather than by the nodes, since
+/// looking up the branches of a node requires access to the arena. Nodes are
+/// transformed in place, so unlike with [`TreeWalk`], rules report whether
+/// they changed anything by returning a [`Dirtiness`].
+///
+/// Only available with the `alloc` feature.
+///
+/// [`TreeWalk`]: crate::TreeWalk
+/// [`Dirtiness`]: crate::Dirtiness
+pub trait ArenaTreeWalk<Id: Copy> {
+    /// Returns an iterator over the ids of the direct branches of the node
+    /// with the given id.
+    fn children(&self, id: Id) -> impl Iterator<Item = Id>;
+
+    /// Applies a function to every node reachable from `root`, including
+    /// `root` itself, in a bottom-up manner.
+    ///
+    /// The function is given the arena and the id of the node, and may modify
+    /// the arena however it wants, such as by overwriting the node or adding
+    /// new ones. The ids of the branches of each node are looked up before
+    /// descending into them, so the function should keep the node at each id
+    /// in place rather than moving it. Returns [`DIRTY`] if the function
+    /// returned [`DIRTY`] for any node.
+    ///
+    /// [`DIRTY`]: Dirtiness::DIRTY
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{ArenaTreeWalk, Dirtiness};
+    /// #[derive(Debug, Clone, Copy, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(usize, usize),
+    /// }
+    /// # use Expr::*;
+    ///
+    /// struct Arena(Vec<Expr>);
+    ///
+    /// impl ArenaTreeWalk<usize> for Arena {
+    ///     fn children(&self, id: usize) -> impl Iterator<Item = usize> {
+    ///         match self.0[id] {
+    ///             Num(_) => None,
+    ///             Add(a, b) => Some([a, b]),
+    ///         }
+    ///         .into_iter()
+    ///         .flatten()
+    ///     }
+    /// }
+    ///
+    /// fn fold_constants(arena: &mut Arena, id: usize) -> Dirtiness {
+    ///     if let Add(a, b) = arena.0[id] {
+    ///         if let (Num(a), Num(b)) = (arena.0[a], arena.0[b]) {
+    ///             arena.0[id] = Num(a + b);
+    ///             return Dirtiness::DIRTY;
+    ///         }
+    ///     }
+    ///     Dirtiness::CLEAN
+    /// }
+    ///
+    /// // (1 + 2) + 3, with the root last
+    /// let mut arena = Arena(vec![Num(1), Num(2), Add(0, 1), Num(3), Add(2, 3)]);
+    ///
+    /// assert_eq!(Dirtiness::DIRTY, arena.bottom_up(4, fold_constants));
+    /// assert_eq!(Num(6), arena.0[4]);
+    /// assert_eq!(Dirtiness::CLEAN, arena.bottom_up(4, fold_constants));
+    /// ```
+    fn bottom_up(
+        &mut self,
+        root: Id,
+        mut f: impl FnMut(&mut Self, Id) -> Dirtiness,
+    ) -> Dirtiness {
+        fn go<A: ArenaTreeWalk<Id> + ?Sized, Id: Copy>(
+            arena: &mut A,
+            id: Id,
+            f: &mut impl FnMut(&mut A, Id) -> Dirtiness,
+        ) -> Dirtiness {
+            let children = arena.children(id).collect::<Vec<_>>();
+            let mut dirtiness = Dirtiness::CLEAN;
+            for child in children {
+                dirtiness |= go(arena, child, f);
+            }
+            dirtiness | f(arena, id)
+        }
+        go(self, root, &mut f)
+    }
+}
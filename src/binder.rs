@@ -0,0 +1,160 @@
+use crate::TreeWalk;
+
+/// Trait for trees containing variable binders, such as lambda abstractions
+/// or `let` expressions, that can be alpha-renamed with [`alpha_rename`].
+///
+/// A binder is a node that introduces a name which is in scope within all of
+/// its branches, as visited by [`each_branch`]. A reference is a node that
+/// refers to a name, and is bound by the innermost binder of that name that
+/// encloses it. References that aren't enclosed by any binder of their name
+/// are free.
+///
+/// [`each_branch`]: TreeWalk::each_branch
+pub trait Binder: TreeWalk<Self> {
+    /// The type of variable names.
+    type Name: Clone;
+
+    /// Returns the name that this node binds, or `None` if it isn't a binder.
+    fn bound_name(&self) -> Option<&Self::Name>;
+
+    /// Returns whether this node is a binder.
+    fn is_binder(&self) -> bool {
+        self.bound_name().is_some()
+    }
+
+    /// Returns whether this node is a reference to the variable `name`.
+    ///
+    /// This should only look at the node itself, not its branches.
+    fn is_reference(&self, name: &Self::Name) -> bool;
+
+    /// Replaces the name that this node binds or refers to with `name`.
+    ///
+    /// This is only called on binders and on nodes for which
+    /// [`is_reference`] returned `true`. It should only change the node
+    /// itself, not its branches.
+    ///
+    /// [`is_reference`]: Binder::is_reference
+    fn rename(self, name: Self::Name) -> Self;
+}
+
+/// Gives every binder in a tree a fresh name, renaming the references bound
+/// by it to match.
+///
+/// `fresh` is called once per binder with its old name, and must return a
+/// name that doesn't occur anywhere else in the tree, including in names it
+/// has previously returned. Given that, the renaming is capture-avoiding:
+/// every reference ends up bound by the same binder as before, shadowed
+/// variables are told apart, and free variables are left alone.
+///
+/// The tree is walked once from the top down, keeping track of the binders
+/// that are in scope along with the fresh names they were given, so `fresh`
+/// is called for outer binders before inner ones. Each reference is checked
+/// against the binders that enclose it, from the innermost outwards, until
+/// one of them binds it.
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{alpha_rename, Binder, TreeWalk};
+/// #[derive(Debug, PartialEq)]
+/// enum Term {
+///     Var(String),
+///     Lam(String, Box<Term>),
+///     App(Box<Term>, Box<Term>),
+/// }
+/// # use Term::*;
+///
+/// impl TreeWalk<Self> for Term {
+///     fn each_branch(self, mut f: impl FnMut(Self) -> Self) -> Self {
+///         match self {
+///             Var(_) => self,
+///             Lam(x, body) => Lam(x, Box::new(f(*body))),
+///             App(a, b) => App(Box::new(f(*a)), Box::new(f(*b))),
+///         }
+///     }
+/// }
+///
+/// impl Binder for Term {
+///     type Name = String;
+///
+///     fn bound_name(&self) -> Option<&String> {
+///         match self {
+///             Lam(x, _) => Some(x),
+///             _ => None,
+///         }
+///     }
+///
+///     fn is_reference(&self, name: &String) -> bool {
+///         matches!(self, Var(x) if x == name)
+///     }
+///
+///     fn rename(self, name: String) -> Self {
+///         match self {
+///             Var(_) => Var(name),
+///             Lam(_, body) => Lam(name, body),
+///             App(..) => self,
+///         }
+///     }
+/// }
+///
+/// # fn var(x: &str) -> Term { Var(x.to_owned()) }
+/// # fn lam(x: &str, body: Term) -> Term { Lam(x.to_owned(), Box::new(body)) }
+/// # fn app(a: Term, b: Term) -> Term { App(Box::new(a), Box::new(b)) }
+/// // λx. (λx. x) (x y)
+/// let term = lam("x", app(lam("x", var("x")), app(var("x"), var("y"))));
+///
+/// let mut counter = 0;
+/// let renamed = alpha_rename(term, |name| {
+///     counter += 1;
+///     format!("{name}{counter}")
+/// });
+///
+/// assert_eq!(
+///     lam("x1", app(lam("x2", var("x2")), app(var("x1"), var("y")))),
+///     renamed,
+/// );
+/// ```
+pub fn alpha_rename<T: Binder>(
+    tree: T,
+    mut fresh: impl FnMut(&T::Name) -> T::Name,
+) -> T {
+    struct Scope<'a, N> {
+        old: N,
+        new: N,
+        outer: Option<&'a Scope<'a, N>>,
+    }
+
+    fn go<T: Binder>(
+        node: T,
+        scope: Option<&Scope<T::Name>>,
+        fresh: &mut impl FnMut(&T::Name) -> T::Name,
+    ) -> T {
+        if let Some(old) = node.bound_name() {
+            let old = old.clone();
+            let new = fresh(&old);
+            let inner = Scope {
+                old,
+                new: new.clone(),
+                outer: scope,
+            };
+            return node
+                .each_branch(|branch| go(branch, Some(&inner), fresh))
+                .rename(new);
+        }
+
+        let mut binder = scope;
+        while let Some(current) = binder {
+            if node.is_reference(&current.old) {
+                break;
+            }
+            binder = current.outer;
+        }
+        let node = match binder {
+            Some(binder) => node.rename(binder.new.clone()),
+            None => node,
+        };
+        node.each_branch(|branch| go(branch, scope, fresh))
+    }
+
+    go(tree, None, &mut fresh)
+}
@@ -0,0 +1,131 @@
+use crate::{Dirtiness, Rewrite};
+
+/// Helper for building a node out of several transformed fields, keeping track
+/// of whether any of them were [`Dirty`].
+///
+/// This is mostly useful when implementing [`TreeWalk`] by hand for nodes with
+/// many branches of different types.
+///
+/// [`Dirty`]: crate::Rewrite::Dirty
+/// [`TreeWalk`]: crate::TreeWalk
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{Rewrite, Clean, Dirty, RewriteBuilder};
+/// #[derive(Debug, PartialEq)]
+/// struct Call {
+///     name: String,
+///     args: Vec<i32>,
+///     pure: bool,
+/// }
+///
+/// let mut builder = RewriteBuilder::new();
+/// let name = builder.field(Clean("f".to_owned()));
+/// let args = builder.field(Dirty(vec![1, 2]));
+/// let pure = builder.field(Clean(true));
+/// let call = builder.build(Call { name, args, pure });
+///
+/// assert!(call.is_dirty());
+/// assert_eq!(vec![1, 2], call.into_inner().args);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub struct RewriteBuilder {
+    dirtiness: Dirtiness,
+}
+
+impl RewriteBuilder {
+    /// Creates a new builder that hasn't seen any [`Dirty`] fields yet.
+    ///
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    pub const fn new() -> Self {
+        Self {
+            dirtiness: Dirtiness::CLEAN,
+        }
+    }
+
+    /// Takes the value out of a transformed field, remembering whether it was
+    /// [`Dirty`].
+    ///
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    pub fn field<T>(&mut self, field: Rewrite<T>) -> T {
+        self.dirtiness |= field.dirtiness();
+        field.into_inner()
+    }
+
+    /// Wraps the finished node in [`Dirty`] if any of the fields were, or in
+    /// [`Clean`] otherwise.
+    ///
+    /// [`Clean`]: crate::Rewrite::Clean
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    pub const fn build<N>(self, node: N) -> Rewrite<N> {
+        Rewrite::with_dirtiness(node, self.dirtiness)
+    }
+}
+
+/// Helper for building a collection one transformed element at a time, keeping
+/// track of whether any of them were [`Dirty`].
+///
+/// This is the incremental counterpart of collecting an iterator of
+/// [`Rewrite`]s into a `Rewrite<C>`.
+///
+/// [`Dirty`]: crate::Rewrite::Dirty
+/// [`Rewrite`]: crate::Rewrite
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{Clean, Dirty, RewriteCollector};
+/// let mut collector = RewriteCollector::<Vec<_>>::default();
+/// collector.push(Clean(1));
+/// collector.push(Dirty(2));
+/// collector.push(Clean(3));
+///
+/// assert_eq!(Dirty(vec![1, 2, 3]), collector.finish());
+///
+/// let mut collector = RewriteCollector::new(String::from("a"));
+/// collector.push(Clean('b'));
+///
+/// assert_eq!(Clean("ab".to_owned()), collector.finish());
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct RewriteCollector<C> {
+    collection: C,
+    dirtiness: Dirtiness,
+}
+
+impl<C> RewriteCollector<C> {
+    /// Creates a new collector that adds to `collection`, which counts as
+    /// [`Clean`].
+    ///
+    /// [`Clean`]: crate::Rewrite::Clean
+    pub const fn new(collection: C) -> Self {
+        Self {
+            collection,
+            dirtiness: Dirtiness::CLEAN,
+        }
+    }
+
+    /// Adds a transformed element to the collection, remembering whether it
+    /// was [`Dirty`].
+    ///
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    pub fn push<T>(&mut self, item: Rewrite<T>)
+    where
+        C: Extend<T>,
+    {
+        self.dirtiness |= item.dirtiness();
+        self.collection.extend(core::iter::once(item.into_inner()));
+    }
+
+    /// Wraps the finished collection in [`Dirty`] if any of the elements were,
+    /// or in [`Clean`] otherwise.
+    ///
+    /// [`Clean`]: crate::Rewrite::Clean
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    pub fn finish(self) -> Rewrite<C> {
+        Rewrite::with_dirtiness(self.collection, self.dirtiness)
+    }
+}
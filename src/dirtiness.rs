@@ -0,0 +1,82 @@
+use core::ops::{BitOr, BitOrAssign};
+
+/// Whether a value was affected by a transformation, without the value
+/// itself.
+///
+/// Dirtiness forms a monoid under `|`, with [`CLEAN`] as the identity and
+/// [`DIRTY`] dominating everything else. This is the same rule that is used
+/// when collecting an iterator of [`Rewrite`]s.
+///
+/// [`CLEAN`]: Dirtiness::CLEAN
+/// [`DIRTY`]: Dirtiness::DIRTY
+/// [`Rewrite`]: crate::Rewrite
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::Dirtiness;
+/// const ALL: [Dirtiness; 2] = [Dirtiness::CLEAN, Dirtiness::DIRTY];
+///
+/// for a in ALL {
+///     // Identity
+///     assert_eq!(a, a | Dirtiness::default());
+///     assert_eq!(a, Dirtiness::default() | a);
+///     for b in ALL {
+///         for c in ALL {
+///             // Associativity
+///             assert_eq!((a | b) | c, a | (b | c));
+///         }
+///     }
+/// }
+///
+/// let mut dirtiness = Dirtiness::CLEAN;
+/// dirtiness |= Dirtiness::DIRTY;
+/// dirtiness |= Dirtiness::CLEAN;
+/// assert!(dirtiness.is_dirty());
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Dirtiness(bool);
+
+impl Dirtiness {
+    /// The value *was not* affected by the transformation.
+    pub const CLEAN: Self = Self(false);
+
+    /// The value *was* affected by the transformation.
+    pub const DIRTY: Self = Self(true);
+
+    /// Returns `true` if the dirtiness is [`CLEAN`].
+    ///
+    /// [`CLEAN`]: Dirtiness::CLEAN
+    #[must_use]
+    pub const fn is_clean(self) -> bool {
+        !self.0
+    }
+
+    /// Returns `true` if the dirtiness is [`DIRTY`].
+    ///
+    /// [`DIRTY`]: Dirtiness::DIRTY
+    #[must_use]
+    pub const fn is_dirty(self) -> bool {
+        self.0
+    }
+}
+
+impl BitOr for Dirtiness {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Dirtiness {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl From<bool> for Dirtiness {
+    fn from(dirty: bool) -> Self {
+        Self(dirty)
+    }
+}
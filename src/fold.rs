@@ -0,0 +1,105 @@
+use alloc::vec::Vec;
+
+/// One layer of a tree, as returned by [`TreeFold::unfold`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Layer<T: TreeFold> {
+    /// A node without branches.
+    Leaf(T::Leaf),
+    /// A node with branches, split into what kind of node it is and the
+    /// branches themselves.
+    Node(T::Node, Vec<T>),
+}
+
+/// Trait for tree-like structures that can be reduced to a value of any type,
+/// one layer at a time.
+///
+/// Unlike [`TreeWalk`], where every node is turned back into the same type,
+/// this makes it possible to turn a tree into something else entirely, such
+/// as a value or a differently typed tree. Implementors only have to say how
+/// to take a single layer of the tree apart.
+///
+/// Only available with the `alloc` feature.
+///
+/// [`TreeWalk`]: crate::TreeWalk
+pub trait TreeFold: Sized {
+    /// The contents of a leaf.
+    type Leaf;
+
+    /// The contents of a node with branches, excluding the branches.
+    type Node;
+
+    /// Splits the tree into its outermost layer.
+    fn unfold(self) -> Layer<Self>;
+
+    /// Reduces the tree to a single value in a bottom-up manner.
+    ///
+    /// `leaf` is applied to every leaf, while `node` is applied to every other
+    /// node along with the results of reducing its branches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Layer, TreeFold};
+    /// enum Op {
+    ///     Add,
+    ///     Mul,
+    /// }
+    ///
+    /// enum Expr {
+    ///     Num(i64),
+    ///     BinOp(Op, Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    ///
+    /// impl TreeFold for Expr {
+    ///     type Leaf = i64;
+    ///     type Node = Op;
+    ///
+    ///     fn unfold(self) -> Layer<Self> {
+    ///         match self {
+    ///             Num(n) => Layer::Leaf(n),
+    ///             BinOp(op, a, b) => Layer::Node(op, vec![*a, *b]),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// // 2 * (3 + 4)
+    /// let tree = BinOp(
+    ///     Op::Mul,
+    ///     Box::new(Num(2)),
+    ///     Box::new(BinOp(Op::Add, Box::new(Num(3)), Box::new(Num(4)))),
+    /// );
+    /// let value = tree.fold_map(
+    ///     |n| n,
+    ///     |op, operands| match op {
+    ///         Op::Add => operands.into_iter().sum(),
+    ///         Op::Mul => operands.into_iter().product(),
+    ///     },
+    /// );
+    ///
+    /// assert_eq!(14, value);
+    /// ```
+    fn fold_map<A>(
+        self,
+        mut leaf: impl FnMut(Self::Leaf) -> A,
+        mut node: impl FnMut(Self::Node, Vec<A>) -> A,
+    ) -> A {
+        fn go<T: TreeFold, A>(
+            tree: T,
+            leaf: &mut impl FnMut(T::Leaf) -> A,
+            node: &mut impl FnMut(T::Node, Vec<A>) -> A,
+        ) -> A {
+            match tree.unfold() {
+                Layer::Leaf(contents) => leaf(contents),
+                Layer::Node(contents, branches) => {
+                    let folded = branches
+                        .into_iter()
+                        .map(|branch| go(branch, leaf, node))
+                        .collect();
+                    node(contents, folded)
+                }
+            }
+        }
+        go(self, &mut leaf, &mut node)
+    }
+}
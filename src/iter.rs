@@ -0,0 +1,416 @@
+use crate::{Dirtiness, Rewrite};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Extension methods for working with [`Rewrite`]s through iterators.
+///
+/// This trait is implemented for every [`Iterator`].
+///
+/// [`Rewrite`]: crate::Rewrite
+/// [`Iterator`]: core::iter::Iterator
+pub trait RewriteIteratorExt: Iterator + Sized {
+    /// Lazily applies a rewriting function to every element of the iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, RewriteIteratorExt};
+    /// fn halve_even(n: i32) -> Rewrite<i32> {
+    ///     if n % 2 == 0 {
+    ///         Dirty(n / 2)
+    ///     } else {
+    ///         Clean(n)
+    ///     }
+    /// }
+    ///
+    /// let mut halved = [1, 4].into_iter().map_rewrite(halve_even);
+    /// assert_eq!(Some(Clean(1)), halved.next());
+    /// assert_eq!(Some(Dirty(2)), halved.next());
+    /// assert_eq!(None, halved.next());
+    ///
+    /// assert_eq!(Clean(vec![1, 3]), [1, 3].into_iter().map_rewrite(halve_even).settle());
+    /// assert_eq!(Dirty(vec![1, 2]), [1, 4].into_iter().map_rewrite(halve_even).settle());
+    /// ```
+    fn map_rewrite<F>(self, f: F) -> RewriteMap<Self, F>
+    where
+        F: FnMut(Self::Item) -> Rewrite<Self::Item>,
+    {
+        RewriteMap { iter: self, f }
+    }
+
+    /// Lazily applies a stateful rewriting function to every element of the
+    /// iterator.
+    ///
+    /// This is like [`Iterator::scan`], except that every element is kept and
+    /// remembers whether it was [`Dirty`].
+    ///
+    /// [`Iterator::scan`]: core::iter::Iterator::scan
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty, RewriteIteratorExt};
+    /// // Merge runs of the same token into their first token
+    /// let tokens = "aabbbc".chars().map(Some);
+    /// let merged = tokens.scan_rewrite(None, |previous, token| {
+    ///     if *previous == token {
+    ///         Dirty(None)
+    ///     } else {
+    ///         *previous = token;
+    ///         Clean(token)
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(
+    ///     vec![
+    ///         Clean(Some('a')),
+    ///         Dirty(None),
+    ///         Clean(Some('b')),
+    ///         Dirty(None),
+    ///         Dirty(None),
+    ///         Clean(Some('c')),
+    ///     ],
+    ///     merged.collect::<Vec<_>>(),
+    /// );
+    /// ```
+    fn scan_rewrite<St, F>(self, init: St, f: F) -> RewriteScan<Self, St, F>
+    where
+        F: FnMut(&mut St, Self::Item) -> Rewrite<Self::Item>,
+    {
+        RewriteScan {
+            iter: self,
+            state: init,
+            f,
+        }
+    }
+
+    /// Yields only the values of [`Dirty`] elements.
+    ///
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty, RewriteIteratorExt};
+    /// let rewrites = [Clean(1), Dirty(2), Clean(3), Dirty(4)];
+    /// assert_eq!(vec![2, 4], rewrites.into_iter().filter_dirty().collect::<Vec<_>>());
+    /// ```
+    fn filter_dirty<T>(self) -> impl Iterator<Item = T>
+    where
+        Self: Iterator<Item = Rewrite<T>>,
+    {
+        self.filter_map(Rewrite::dirty)
+    }
+
+    /// Yields only the values of [`Clean`] elements.
+    ///
+    /// [`Clean`]: crate::Rewrite::Clean
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty, RewriteIteratorExt};
+    /// let rewrites = [Clean(1), Dirty(2), Clean(3), Dirty(4)];
+    /// assert_eq!(vec![1, 3], rewrites.into_iter().filter_clean().collect::<Vec<_>>());
+    /// ```
+    fn filter_clean<T>(self) -> impl Iterator<Item = T>
+    where
+        Self: Iterator<Item = Rewrite<T>>,
+    {
+        self.filter_map(Rewrite::clean)
+    }
+
+    /// Yields only the key-value pairs whose values are [`Dirty`], with the
+    /// values unwrapped.
+    ///
+    /// This works with any collection of pairs, such as the entries of a map
+    /// or an association list, and can be collected back into one that only
+    /// holds the changed entries.
+    ///
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::collections::{BTreeMap, HashMap};
+    /// # use trexp::{Clean, Dirty, RewriteIteratorExt};
+    /// let tree_map = BTreeMap::from([("a", Clean(1)), ("b", Dirty(2))]);
+    /// let changed = tree_map.into_iter().filter_dirty_values().collect::<BTreeMap<_, _>>();
+    /// assert_eq!(BTreeMap::from([("b", 2)]), changed);
+    ///
+    /// let hash_map = HashMap::from([("a", Dirty(1)), ("b", Clean(2))]);
+    /// let changed = hash_map.into_iter().filter_dirty_values().collect::<HashMap<_, _>>();
+    /// assert_eq!(HashMap::from([("a", 1)]), changed);
+    ///
+    /// let assoc = vec![("a", Dirty(1)), ("b", Clean(2)), ("a", Dirty(3))];
+    /// let changed = assoc.into_iter().filter_dirty_values().collect::<Vec<_>>();
+    /// assert_eq!(vec![("a", 1), ("a", 3)], changed);
+    /// ```
+    fn filter_dirty_values<K, V>(self) -> impl Iterator<Item = (K, V)>
+    where
+        Self: Iterator<Item = (K, Rewrite<V>)>,
+    {
+        self.filter_map(|(key, value)| Some((key, value.dirty()?)))
+    }
+}
+
+impl<I: Iterator> RewriteIteratorExt for I {}
+
+/// An iterator that applies a rewriting function to every element of another
+/// iterator.
+///
+/// This `struct` is created by [`RewriteIteratorExt::map_rewrite`].
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+#[derive(Clone)]
+pub struct RewriteMap<I, F> {
+    iter: I,
+    f: F,
+}
+
+impl<I, F> RewriteMap<I, F>
+where
+    I: Iterator,
+    F: FnMut(I::Item) -> Rewrite<I::Item>,
+{
+    /// Collects the rewritten elements into a [`Vec`], which is [`Dirty`] if
+    /// any of the elements are.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// [`Vec`]: alloc::vec::Vec
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    #[cfg(feature = "alloc")]
+    pub fn settle(self) -> Rewrite<Vec<I::Item>> {
+        self.collect()
+    }
+}
+
+impl<I: fmt::Debug, F> fmt::Debug for RewriteMap<I, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RewriteMap")
+            .field("iter", &self.iter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<I, F> Iterator for RewriteMap<I, F>
+where
+    I: Iterator,
+    F: FnMut(I::Item) -> Rewrite<I::Item>,
+{
+    type Item = Rewrite<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(&mut self.f)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// An iterator that applies a stateful rewriting function to every element of
+/// another iterator.
+///
+/// This `struct` is created by [`RewriteIteratorExt::scan_rewrite`].
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+#[derive(Clone)]
+pub struct RewriteScan<I, St, F> {
+    iter: I,
+    state: St,
+    f: F,
+}
+
+impl<I: fmt::Debug, St: fmt::Debug, F> fmt::Debug for RewriteScan<I, St, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RewriteScan")
+            .field("iter", &self.iter)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<I, St, F> Iterator for RewriteScan<I, St, F>
+where
+    I: Iterator,
+    F: FnMut(&mut St, I::Item) -> Rewrite<I::Item>,
+{
+    type Item = Rewrite<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        Some((self.f)(&mut self.state, item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// Extends a collection with the values of an iterator of rewrites, returning
+/// whether any of them were [`Dirty`].
+///
+/// This is like collecting into a `Rewrite<C>`, but reuses an existing
+/// collection instead of creating a new one.
+///
+/// [`Dirty`]: crate::Rewrite::Dirty
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{Clean, Dirty, Dirtiness, extend_from_rewrites};
+/// let mut numbers = vec![1];
+///
+/// let dirtiness = extend_from_rewrites(&mut numbers, [Clean(2), Dirty(3)]);
+/// assert_eq!(Dirtiness::DIRTY, dirtiness);
+///
+/// let dirtiness = extend_from_rewrites(&mut numbers, [Clean(4)]);
+/// assert_eq!(Dirtiness::CLEAN, dirtiness);
+///
+/// assert_eq!(vec![1, 2, 3, 4], numbers);
+/// ```
+pub fn extend_from_rewrites<T>(
+    dst: &mut impl Extend<T>,
+    iter: impl IntoIterator<Item = Rewrite<T>>,
+) -> Dirtiness {
+    let mut dirtiness = Dirtiness::CLEAN;
+    dst.extend(
+        iter.into_iter()
+            .inspect(|item| dirtiness |= item.dirtiness())
+            .map(Rewrite::into_inner),
+    );
+    dirtiness
+}
+
+/// Collects an iterator of key-value pairs with rewritten values into a map,
+/// which is [`Dirty`] if any of the values are.
+///
+/// This works for any collection of pairs, such as a [`HashMap`] or a
+/// [`BTreeMap`].
+///
+/// [`Dirty`]: crate::Rewrite::Dirty
+/// [`HashMap`]: https://doc.rust-lang.org/std/collections/struct.HashMap.html
+/// [`BTreeMap`]: https://doc.rust-lang.org/alloc/collections/btree_map/struct.BTreeMap.html
+///
+/// # Examples
+///
+/// ```
+/// # use std::collections::{BTreeMap, HashMap};
+/// # use trexp::{Rewrite, Clean, Dirty, collect_map_rewrites};
+/// let clean: Rewrite<HashMap<_, _>> =
+///     collect_map_rewrites([("a", Clean(1)), ("b", Clean(2))]);
+/// assert_eq!(Clean(HashMap::from([("a", 1), ("b", 2)])), clean);
+///
+/// let mixed: Rewrite<BTreeMap<_, _>> =
+///     collect_map_rewrites([("a", Clean(1)), ("b", Dirty(2))]);
+/// assert_eq!(Dirty(BTreeMap::from([("a", 1), ("b", 2)])), mixed);
+/// ```
+pub fn collect_map_rewrites<K, V, M: FromIterator<(K, V)>>(
+    iter: impl IntoIterator<Item = (K, Rewrite<V>)>,
+) -> Rewrite<M> {
+    iter.into_iter()
+        .map(|(key, value)| value.map(|value| (key, value)))
+        .collect()
+}
+
+/// Enumerates every way of picking one candidate from each list of
+/// candidates, which is [`Dirty`] if any of the lists are.
+///
+/// Combinations are produced in lexicographic order. An empty list of lists
+/// has a single combination: the empty one.
+///
+/// Only available with the `alloc` feature.
+///
+/// [`Dirty`]: crate::Rewrite::Dirty
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{Clean, Dirty, cartesian_product};
+/// let products = cartesian_product([Clean(vec!['a', 'b']), Dirty(vec!['x', 'y'])]);
+/// assert_eq!(
+///     Dirty(vec![
+///         vec!['a', 'x'],
+///         vec!['a', 'y'],
+///         vec!['b', 'x'],
+///         vec!['b', 'y'],
+///     ]),
+///     products,
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn cartesian_product<T: Clone>(
+    candidates: impl IntoIterator<Item = Rewrite<Vec<T>>>,
+) -> Rewrite<Vec<Vec<T>>> {
+    let mut dirtiness = Dirtiness::CLEAN;
+    let mut products = Vec::from([Vec::new()]);
+    for candidates in candidates {
+        dirtiness |= candidates.dirtiness();
+        let candidates = candidates.into_inner();
+        products = products
+            .iter()
+            .flat_map(|prefix| {
+                candidates.iter().map(move |candidate| {
+                    let mut product = prefix.clone();
+                    product.push(candidate.clone());
+                    product
+                })
+            })
+            .collect();
+    }
+    Rewrite::with_dirtiness(products, dirtiness)
+}
+
+/// Collects fallible rewrites into a vector, reporting every error instead of
+/// just the first one.
+///
+/// Returns `Err` with all of the errors in order if there were any. Otherwise,
+/// returns the values, which are [`Dirty`] if any of them were.
+///
+/// Only available with the `alloc` feature.
+///
+/// [`Dirty`]: crate::Rewrite::Dirty
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{Clean, Dirty, collect_errors};
+/// let parse = |s: &'static str| s.parse::<i32>().map_err(|_| s);
+///
+/// let parsed = collect_errors([
+///     Clean(parse("1")),
+///     Clean(parse("x")),
+///     Dirty(parse("2")),
+///     Dirty(parse("y")),
+///     Clean(parse("3")),
+/// ]);
+/// assert_eq!(Err(vec!["x", "y"]), parsed);
+///
+/// let parsed = collect_errors([Clean(Ok::<_, ()>(1)), Dirty(Ok(2))]);
+/// assert_eq!(Ok(Dirty(vec![1, 2])), parsed);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn collect_errors<T, E>(
+    iter: impl IntoIterator<Item = Rewrite<Result<T, E>>>,
+) -> Result<Rewrite<Vec<T>>, Vec<E>> {
+    let mut dirtiness = Dirtiness::CLEAN;
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    for item in iter {
+        let item_dirtiness = item.dirtiness();
+        match item.into_inner() {
+            Ok(value) => {
+                dirtiness |= item_dirtiness;
+                values.push(value);
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+    if errors.is_empty() {
+        Ok(Rewrite::with_dirtiness(values, dirtiness))
+    } else {
+        Err(errors)
+    }
+}
@@ -5,10 +5,36 @@
 
 //! Utilities for transforming expression trees.
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+pub mod arena;
 pub mod bind;
+pub mod binder;
+pub mod builder;
+pub mod dirtiness;
+#[cfg(feature = "alloc")]
+pub mod fold;
+pub mod iter;
+pub mod prelude;
 pub mod rewrite;
+pub mod rule;
+pub mod spanned;
 pub mod tree;
 
+#[cfg(feature = "alloc")]
+pub use arena::*;
 pub use bind::*;
+pub use binder::*;
+pub use builder::*;
+pub use dirtiness::*;
+#[cfg(feature = "alloc")]
+pub use fold::*;
+pub use iter::*;
 pub use rewrite::*;
+pub use rule::*;
+pub use spanned::*;
 pub use tree::*;
@@ -0,0 +1,46 @@
+//! Re-exports of the most commonly used items, meant to be glob imported.
+//!
+//! Items that depend on a feature are only re-exported when it is enabled.
+//!
+//! # Examples
+//!
+//! ```
+//! use trexp::prelude::*;
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Expr {
+//!     Num(i32),
+//!     Neg(Box<Expr>),
+//! }
+//! # use Expr::*;
+//!
+//! impl TreeWalk<Rewrite<Self>> for Expr {
+//!     fn each_branch(
+//!         self,
+//!         mut f: impl FnMut(Self) -> Rewrite<Self>,
+//!     ) -> Rewrite<Self> {
+//!         match self {
+//!             Num(_) => Clean(self),
+//!             Neg(inner) => f(*inner).map(|inner| Neg(Box::new(inner))),
+//!         }
+//!     }
+//! }
+//!
+//! let fold_negation = |expr| match expr {
+//!     Neg(inner) => match *inner {
+//!         Num(n) => Dirty(Num(-n)),
+//!         inner => Clean(Neg(Box::new(inner))),
+//!     },
+//!     _ => Clean(expr),
+//! };
+//!
+//! let tree = Neg(Box::new(Neg(Box::new(Num(3)))));
+//! assert_eq!(Dirty(Num(3)), tree.bottom_up(fold_negation));
+//! ```
+
+#[cfg(feature = "alloc")]
+pub use crate::{ArenaTreeWalk, ChildVec, TreeFold};
+pub use crate::{
+    Bind, Children, Clean, Dirtiness, Dirty, ResultRewriteExt, Rewrite,
+    RewriteIteratorExt, Rule, TreeRewrite, TreeTransform, TreeWalk,
+};
@@ -1,7 +1,13 @@
 pub use Rewrite::{Clean, Dirty};
 
-use crate::Bind;
-use core::ops::{Deref, DerefMut};
+use crate::{Bind, Dirtiness};
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{
+    fmt::{self, Debug, Display, Formatter},
+    future::Future,
+    ops::{ControlFlow, Deref, DerefMut},
+};
 
 /// Enum representing a value that has passed through a transformation that may
 /// or may not have affected it.
@@ -13,6 +19,54 @@ pub enum Rewrite<T> {
     Dirty(T),
 }
 
+/// Unwraps a [`Dirty`] value, or returns a [`Clean`] one from the enclosing
+/// function.
+///
+/// This makes it possible to chain several rewriting steps that must all fire
+/// for the combined rule to fire, similarly to how `?` works for [`Option`].
+/// Note that the *whole function* returns as soon as a step is [`Clean`], and
+/// that it returns the value as it was after the last step, marked as
+/// [`Clean`] even if earlier steps changed it. Steps should therefore hand
+/// back their input unchanged when they don't fire.
+///
+/// [`Clean`]: crate::Rewrite::Clean
+/// [`Dirty`]: crate::Rewrite::Dirty
+/// [`Option`]: core::option::Option
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{Rewrite, Clean, Dirty, try_dirty};
+/// fn halve_even(n: i32) -> Rewrite<i32> {
+///     if n % 2 == 0 {
+///         Dirty(n / 2)
+///     } else {
+///         Clean(n)
+///     }
+/// }
+///
+/// fn quarter(n: i32) -> Rewrite<i32> {
+///     let n = try_dirty!(halve_even(n));
+///     let n = try_dirty!(halve_even(n));
+///     Dirty(n)
+/// }
+///
+/// assert_eq!(Dirty(3), quarter(12));
+/// assert_eq!(Clean(3), quarter(6)); // The second step didn't fire
+/// assert_eq!(Clean(5), quarter(5)); // The first step didn't fire
+/// ```
+#[macro_export]
+macro_rules! try_dirty {
+    ($expr:expr $(,)?) => {
+        match $expr {
+            $crate::Rewrite::Dirty(dirty) => dirty,
+            $crate::Rewrite::Clean(clean) => {
+                return $crate::Rewrite::Clean(clean);
+            }
+        }
+    };
+}
+
 impl<T> Rewrite<T> {
     /// Returns `true` if the rewrite is [`Clean`].
     ///
@@ -30,6 +84,48 @@ impl<T> Rewrite<T> {
         matches!(self, Dirty(..))
     }
 
+    /// Wraps a value in [`Clean`] or [`Dirty`] depending on `dirtiness`.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, Dirtiness};
+    /// assert_eq!(Clean(1), Rewrite::with_dirtiness(1, Dirtiness::CLEAN));
+    /// assert_eq!(Dirty(1), Rewrite::with_dirtiness(1, Dirtiness::DIRTY));
+    /// ```
+    pub const fn with_dirtiness(value: T, dirtiness: Dirtiness) -> Self {
+        if dirtiness.is_dirty() {
+            Dirty(value)
+        } else {
+            Clean(value)
+        }
+    }
+
+    /// Returns whether the rewrite is [`Clean`] or [`Dirty`] as a
+    /// [`Dirtiness`].
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    /// [`Dirtiness`]: crate::Dirtiness
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty, Dirtiness};
+    /// assert_eq!(Dirtiness::CLEAN, Clean(1).dirtiness());
+    /// assert_eq!(Dirtiness::DIRTY, Dirty(1).dirtiness());
+    /// ```
+    #[must_use]
+    pub const fn dirtiness(&self) -> Dirtiness {
+        match self {
+            Clean(..) => Dirtiness::CLEAN,
+            Dirty(..) => Dirtiness::DIRTY,
+        }
+    }
+
     /// Takes the contained value, forgetting whether it's [`Clean`] or
     /// [`Dirty`].
     ///
@@ -43,6 +139,140 @@ impl<T> Rewrite<T> {
         }
     }
 
+    /// Converts `self` into an [`Option`], returning the value only if it's
+    /// [`Dirty`].
+    ///
+    /// [`Option`]: core::option::Option
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Some(1), Dirty(1).dirty());
+    /// assert_eq!(None, Clean(1).dirty());
+    /// ```
+    pub fn dirty(self) -> Option<T> {
+        match self {
+            Clean(_) => None,
+            Dirty(t) => Some(t),
+        }
+    }
+
+    /// Converts `self` into an [`Option`], returning the value only if it's
+    /// [`Clean`].
+    ///
+    /// [`Option`]: core::option::Option
+    /// [`Clean`]: Rewrite::Clean
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Some(1), Clean(1).clean());
+    /// assert_eq!(None, Dirty(1).clean());
+    /// ```
+    pub fn clean(self) -> Option<T> {
+        match self {
+            Clean(t) => Some(t),
+            Dirty(_) => None,
+        }
+    }
+
+    /// Converts `self` into a [`ControlFlow`], mapping [`Dirty`] to [`Break`]
+    /// and [`Clean`] to [`Continue`].
+    ///
+    /// This makes it possible to stop an [`Iterator::try_fold`] as soon as
+    /// something has been changed.
+    ///
+    /// [`ControlFlow`]: core::ops::ControlFlow
+    /// [`Break`]: core::ops::ControlFlow::Break
+    /// [`Continue`]: core::ops::ControlFlow::Continue
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    /// [`Iterator::try_fold`]: core::iter::Iterator::try_fold
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use core::ops::ControlFlow;
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// fn halve_even(n: i32) -> Rewrite<i32> {
+    ///     if n % 2 == 0 {
+    ///         Dirty(n / 2)
+    ///     } else {
+    ///         Clean(n)
+    ///     }
+    /// }
+    ///
+    /// let mut visited = 0;
+    /// let first_halved = [1, 3, 8, 5, 6].into_iter().try_fold(0, |_, n| {
+    ///     visited += 1;
+    ///     halve_even(n).into_control_flow()
+    /// });
+    ///
+    /// assert_eq!(ControlFlow::Break(4), first_halved);
+    /// assert_eq!(3, visited);
+    /// ```
+    #[allow(clippy::missing_const_for_fn)] // requires const_precise_live_drops
+    pub fn into_control_flow(self) -> ControlFlow<T, T> {
+        match self {
+            Clean(t) => ControlFlow::Continue(t),
+            Dirty(t) => ControlFlow::Break(t),
+        }
+    }
+
+    /// Converts `self` into a [`Result`], mapping [`Dirty`] to `Ok` and
+    /// [`Clean`] to `Err`.
+    ///
+    /// This fits rules where not firing is the failure case, such as when
+    /// trying to make progress. See [`clean_ok`] for the opposite mapping.
+    ///
+    /// [`Result`]: core::result::Result
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    /// [`clean_ok`]: Rewrite::clean_ok
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Ok(1), Dirty(1).dirty_ok());
+    /// assert_eq!(Err(1), Clean(1).dirty_ok());
+    /// ```
+    pub fn dirty_ok(self) -> Result<T, T> {
+        match self {
+            Clean(t) => Err(t),
+            Dirty(t) => Ok(t),
+        }
+    }
+
+    /// Converts `self` into a [`Result`], mapping [`Clean`] to `Ok` and
+    /// [`Dirty`] to `Err`.
+    ///
+    /// This fits checks where any change is the failure case, such as when
+    /// verifying that something is already normalized. See [`dirty_ok`] for
+    /// the opposite mapping.
+    ///
+    /// [`Result`]: core::result::Result
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    /// [`dirty_ok`]: Rewrite::dirty_ok
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Ok(1), Clean(1).clean_ok());
+    /// assert_eq!(Err(1), Dirty(1).clean_ok());
+    /// ```
+    pub fn clean_ok(self) -> Result<T, T> {
+        match self {
+            Clean(t) => Ok(t),
+            Dirty(t) => Err(t),
+        }
+    }
+
     /// Borrows the inner value.
     pub const fn inner_ref(&self) -> &T {
         let (Clean(t) | Dirty(t)) = self;
@@ -50,7 +280,7 @@ impl<T> Rewrite<T> {
     }
 
     /// Mutably borrows the inner value.
-    pub fn inner_mut(&mut self) -> &mut T {
+    pub const fn inner_mut(&mut self) -> &mut T {
         let (Clean(t) | Dirty(t)) = self;
         t
     }
@@ -64,7 +294,7 @@ impl<T> Rewrite<T> {
     }
 
     /// Converts from `&mut Rewrite<T>` to `Rewrite<&mut T>`.
-    pub fn as_mut(&mut self) -> Rewrite<&mut T> {
+    pub const fn as_mut(&mut self) -> Rewrite<&mut T> {
         match self {
             Clean(t) => Clean(t),
             Dirty(t) => Dirty(t),
@@ -83,7 +313,18 @@ impl<T> Rewrite<T> {
     }
 
     /// Converts from `&mut Rewrite<T>` to `Rewrite<&mut T::Target>`.
-    pub fn as_deref_mut(&mut self) -> Rewrite<&T::Target>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Dirty};
+    /// let mut boxed: Rewrite<Box<i32>> = Dirty(Box::new(1));
+    /// if let Dirty(n) = boxed.as_deref_mut() {
+    ///     *n += 1;
+    /// }
+    /// assert_eq!(Dirty(Box::new(2)), boxed);
+    /// ```
+    pub fn as_deref_mut(&mut self) -> Rewrite<&mut T::Target>
     where
         T: DerefMut,
     {
@@ -105,6 +346,52 @@ impl<T> Rewrite<T> {
         }
     }
 
+    /// Maps a function over a reference to the inner value, retaining the
+    /// variant and leaving `self` untouched.
+    ///
+    /// This is a shorthand for `self.as_ref().map(f)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let name = Dirty("trexp".to_owned());
+    ///
+    /// assert_eq!(Dirty(5), name.map_ref(String::len));
+    /// assert_eq!(Dirty("trexp".to_owned()), name);
+    /// ```
+    pub fn map_ref<U>(&self, f: impl FnOnce(&T) -> U) -> Rewrite<U> {
+        self.as_ref().map(f)
+    }
+
+    /// Maps one of two functions over `self` depending on whether it's
+    /// [`Clean`] or [`Dirty`], retaining the variant.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let describe = |r: Rewrite<i32>| {
+    ///     r.map_clean_or_dirty(|n| format!("kept {n}"), |n| format!("got {n}"))
+    /// };
+    ///
+    /// assert_eq!(Clean("kept 1".to_owned()), describe(Clean(1)));
+    /// assert_eq!(Dirty("got 2".to_owned()), describe(Dirty(2)));
+    /// ```
+    pub fn map_clean_or_dirty<U>(
+        self,
+        if_clean: impl FnOnce(T) -> U,
+        if_dirty: impl FnOnce(T) -> U,
+    ) -> Rewrite<U> {
+        match self {
+            Clean(t) => Clean(if_clean(t)),
+            Dirty(t) => Dirty(if_dirty(t)),
+        }
+    }
+
     /// Repeatedly applies a function until its result is [`Clean`].
     ///
     /// [`Clean`]: Rewrite::Clean
@@ -126,88 +413,1185 @@ impl<T> Rewrite<T> {
     /// assert_eq!(Dirty(5), halve_repeatedly(10)); // Succeeded once
     /// assert_eq!(Dirty(3), halve_repeatedly(24)); // Succeeded thrice
     /// assert_eq!(Clean(7), halve_repeatedly(7)); // Didn't succeed
+    ///
+    /// // The function is applied once more to confirm the fixpoint
+    /// let mut calls = 0;
+    /// Rewrite::repeat(24, |n| {
+    ///     calls += 1;
+    ///     halve_even(n)
+    /// });
+    /// assert_eq!(4, calls);
     /// ```
     pub fn repeat(initial: T, mut f: impl FnMut(T) -> Self) -> Self {
-        let mut val = Clean(initial);
+        let mut val = match f(initial) {
+            Clean(done) => return Clean(done),
+            Dirty(keep_going) => keep_going,
+        };
         loop {
-            match val.map(&mut f).transpose() {
-                Clean(done) => break done,
-                Dirty(keep_going) => val = Dirty(keep_going.into_inner()),
+            match f(val) {
+                Clean(done) => break Dirty(done),
+                Dirty(keep_going) => val = keep_going,
             }
         }
     }
 
-    /// A version of [`repeat`] that takes a fallible function.
+    /// A version of [`repeat`] for asynchronous functions, which awaits each
+    /// step until the function returns [`Clean`].
+    ///
+    /// Unlike [`repeat`], this only returns the final value, without
+    /// remembering whether any of the steps were [`Dirty`].
+    ///
+    /// This is available without any features, since it only needs
+    /// [`Future`] from `core`. It doesn't depend on any particular executor,
+    /// so any async runtime can drive it.
     ///
     /// [`repeat`]: Rewrite::repeat
-    pub fn try_repeat<E>(
+    /// [`Future`]: core::future::Future
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// # use std::{
+    /// #     future::Future,
+    /// #     pin::pin,
+    /// #     sync::Arc,
+    /// #     task::{Context, Poll, Wake},
+    /// #     thread::{self, Thread},
+    /// # };
+    /// #
+    /// # struct ThreadWaker(Thread);
+    /// #
+    /// # impl Wake for ThreadWaker {
+    /// #     fn wake(self: Arc<Self>) {
+    /// #         self.0.unpark();
+    /// #     }
+    /// # }
+    /// #
+    /// # fn block_on<F: Future>(future: F) -> F::Output {
+    /// #     let mut future = pin!(future);
+    /// #     let waker = Arc::new(ThreadWaker(thread::current())).into();
+    /// #     let mut cx = Context::from_waker(&waker);
+    /// #     loop {
+    /// #         match future.as_mut().poll(&mut cx) {
+    /// #             Poll::Ready(output) => break output,
+    /// #             Poll::Pending => thread::park(),
+    /// #         }
+    /// #     }
+    /// # }
+    /// async fn halve_even(n: i32) -> Rewrite<i32> {
+    ///     if n % 2 == 0 {
+    ///         Dirty(n / 2)
+    ///     } else {
+    ///         Clean(n)
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(3, block_on(Rewrite::repeat_async(24, halve_even)));
+    /// assert_eq!(7, block_on(Rewrite::repeat_async(7, halve_even)));
+    /// ```
+    pub async fn repeat_async<Fut: Future<Output = Self>>(
         initial: T,
-        mut f: impl FnMut(T) -> Result<Self, E>,
-    ) -> Result<Self, E> {
-        let mut val = Clean(initial);
+        mut f: impl FnMut(T) -> Fut,
+    ) -> T {
+        let mut val = initial;
         loop {
-            match val.map(&mut f).transpose_result()?.transpose() {
-                Clean(done) => break Ok(done),
-                Dirty(keep_going) => val = Dirty(keep_going.into_inner()),
+            match f(val).await {
+                Clean(done) => break done,
+                Dirty(keep_going) => val = keep_going,
             }
         }
     }
 
-    /// Applies a function and makes the result [`Dirty`] if `self` was already
-    /// dirty or became dirty as a result of the function.
+    /// Applies a function exactly `n` times, regardless of whether it returns
+    /// [`Clean`] or [`Dirty`].
+    ///
+    /// The result is [`Dirty`] if any of the applications were. If `n` is
+    /// zero, the function is never applied and `Clean(initial)` is returned.
     ///
+    /// [`Clean`]: Rewrite::Clean
     /// [`Dirty`]: Rewrite::Dirty
-    pub fn bind<U>(self, f: impl FnOnce(T) -> Rewrite<U>) -> Rewrite<U> {
-        match self {
-            Clean(clean) => f(clean),
-            Dirty(dirty) => Dirty(f(dirty).into_inner()),
-        }
-    }
-
-    /// A version of [`bind`] that takes a fallible function.
     ///
-    /// [`bind`]: Rewrite::bind
-    pub fn try_bind<E, U>(
-        self,
-        f: impl FnOnce(T) -> Result<Rewrite<U>, E>,
-    ) -> Result<Rewrite<U>, E> {
-        match self {
-            Clean(t) => f(t),
-            Dirty(t) => Ok(Dirty(f(t)?.into_inner())),
-        }
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let halve_even = |n: i32| if n % 2 == 0 { Dirty(n / 2) } else { Clean(n) };
+    ///
+    /// assert_eq!(Clean(24), Rewrite::apply_n(24, 0, halve_even));
+    /// assert_eq!(Dirty(12), Rewrite::apply_n(24, 1, halve_even));
+    /// assert_eq!(Dirty(3), Rewrite::apply_n(24, 3, halve_even));
+    /// assert_eq!(Clean(3), Rewrite::apply_n(3, 3, halve_even));
+    /// ```
+    pub fn apply_n(initial: T, n: usize, mut f: impl FnMut(T) -> Self) -> Self {
+        (0..n).fold(Clean(initial), |val, _| val.bind(&mut f))
     }
-}
 
-impl<T> Rewrite<&T> {
-    /// Creates a new [`Rewrite`] by copying the inner value.
+    /// A version of [`repeat`] that returns an iterator over every value that
+    /// the computation passes through, starting with `initial` and ending with
+    /// the fixpoint.
     ///
-    /// [`Rewrite`]: Rewrite
-    #[must_use]
-    pub const fn copied(self) -> Rewrite<T>
+    /// The function is applied lazily, one step ahead of the iterator.
+    ///
+    /// [`repeat`]: Rewrite::repeat
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// fn halve_even(n: i32) -> Rewrite<i32> {
+    ///     if n % 2 == 0 {
+    ///         Dirty(n / 2)
+    ///     } else {
+    ///         Clean(n)
+    ///     }
+    /// }
+    ///
+    /// let steps = Rewrite::repeat_iter(24, halve_even).collect::<Vec<_>>();
+    /// assert_eq!(vec![24, 12, 6, 3], steps);
+    /// ```
+    pub fn repeat_iter(
+        initial: T,
+        mut f: impl FnMut(T) -> Self,
+    ) -> impl Iterator<Item = T>
     where
-        T: Copy,
+        T: Clone,
     {
-        match self {
-            Clean(t) => Clean(*t),
-            Dirty(t) => Dirty(*t),
-        }
+        let mut next = Some(initial);
+        core::iter::from_fn(move || {
+            let current = next.take()?;
+            next = f(current.clone()).dirty();
+            Some(current)
+        })
     }
 
-    /// Creates a new [`Rewrite`] by cloning the inner value.
+    /// A version of [`repeat`] that takes a fallible function.
     ///
-    /// [`Rewrite`]: Rewrite
-    pub fn cloned(self) -> Rewrite<T>
-    where
-        T: Clone,
-    {
-        self.map(Clone::clone)
+    /// [`repeat`]: Rewrite::repeat
+    pub fn try_repeat<E>(
+        initial: T,
+        mut f: impl FnMut(T) -> Result<Self, E>,
+    ) -> Result<Self, E> {
+        let mut val = Clean(initial);
+        loop {
+            match val.map(&mut f).transpose_result()?.transpose() {
+                Clean(done) => break Ok(done),
+                Dirty(keep_going) => val = Dirty(keep_going.into_inner()),
+            }
+        }
     }
-}
 
-impl<T> Rewrite<Rewrite<T>> {
-    /// Swaps two layers of [`Rewrite`].
+    /// A version of [`repeat`] that gives up after applying the function `max`
+    /// times.
     ///
-    /// [`Rewrite`]: Rewrite
+    /// Returns `Ok` with the fixpoint if the function returned [`Clean`] within
+    /// the limit, or `Err` with the last value if the limit was hit first. A
+    /// `max` of zero never applies the function and always returns `Err`.
+    ///
+    /// [`repeat`]: Rewrite::repeat
+    /// [`Clean`]: Rewrite::Clean
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let countdown = |n: u32| if n == 0 { Clean(n) } else { Dirty(n - 1) };
+    ///
+    /// assert_eq!(Ok(0), Rewrite::repeat_bounded(3, 10, countdown));
+    /// assert_eq!(Err(7), Rewrite::repeat_bounded(10, 3, countdown));
+    /// ```
+    pub fn repeat_bounded(
+        initial: T,
+        max: usize,
+        mut f: impl FnMut(T) -> Self,
+    ) -> Result<T, T> {
+        let mut val = initial;
+        for _ in 0..max {
+            match f(val) {
+                Clean(done) => return Ok(done),
+                Dirty(keep_going) => val = keep_going,
+            }
+        }
+        Err(val)
+    }
+
+    /// A version of [`repeat_bounded`] that returns the final value either
+    /// way, along with whether a fixpoint was reached.
+    ///
+    /// The `bool` is `true` if the function returned [`Clean`] within the
+    /// limit and `false` if the limit was hit first.
+    ///
+    /// [`repeat_bounded`]: Rewrite::repeat_bounded
+    /// [`Clean`]: Rewrite::Clean
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let countdown = |n: u32| if n == 0 { Clean(n) } else { Dirty(n - 1) };
+    ///
+    /// assert_eq!((0, true), Rewrite::saturating_repeat(3, 10, countdown));
+    /// assert_eq!((7, false), Rewrite::saturating_repeat(10, 3, countdown));
+    /// ```
+    pub fn saturating_repeat(
+        initial: T,
+        max: usize,
+        f: impl FnMut(T) -> Self,
+    ) -> (T, bool) {
+        match Self::repeat_bounded(initial, max, f) {
+            Ok(done) => (done, true),
+            Err(last) => (last, false),
+        }
+    }
+
+    /// A version of [`repeat`] that calls a hook every `every` applications of
+    /// the function, which can stop the repetition early.
+    ///
+    /// The hook is given the number of applications so far and the current
+    /// value, and returns `false` to stop. The value at that point is returned
+    /// as is, just like a fixpoint would be. If `every` is zero, the hook is
+    /// never called. This is useful for reporting progress or checking for
+    /// cancellation during long computations.
+    ///
+    /// [`repeat`]: Rewrite::repeat
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let countdown = |n: u32| if n == 0 { Clean(n) } else { Dirty(n - 1) };
+    ///
+    /// let mut progress = Vec::new();
+    /// let done = Rewrite::repeat_with_hook(
+    ///     10,
+    ///     4,
+    ///     |applications, n| {
+    ///         progress.push((applications, *n));
+    ///         true
+    ///     },
+    ///     countdown,
+    /// );
+    /// assert_eq!(0, done);
+    /// assert_eq!(vec![(4, 6), (8, 2)], progress);
+    ///
+    /// let aborted = Rewrite::repeat_with_hook(10, 3, |_, n| *n > 5, countdown);
+    /// assert_eq!(4, aborted);
+    /// ```
+    pub fn repeat_with_hook(
+        initial: T,
+        every: usize,
+        mut hook: impl FnMut(usize, &T) -> bool,
+        mut f: impl FnMut(T) -> Self,
+    ) -> T {
+        let mut val = initial;
+        let mut applications = 0;
+        loop {
+            val = match f(val) {
+                Clean(done) => break done,
+                Dirty(keep_going) => keep_going,
+            };
+            applications += 1;
+            if every != 0
+                && applications % every == 0
+                && !hook(applications, &val)
+            {
+                break val;
+            }
+        }
+    }
+
+    /// A version of [`repeat_bounded`] that takes a fallible function.
+    ///
+    /// The outer `Result` carries errors from the function, while the inner one
+    /// is the same as for [`repeat_bounded`].
+    ///
+    /// [`repeat_bounded`]: Rewrite::repeat_bounded
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let countdown = |n: i32| match n {
+    ///     0 => Ok(Clean(n)),
+    ///     n if n < 0 => Err("negative"),
+    ///     n => Ok(Dirty(n - 1)),
+    /// };
+    ///
+    /// assert_eq!(Ok(Ok(0)), Rewrite::try_repeat_bounded(3, 10, countdown));
+    /// assert_eq!(Ok(Err(7)), Rewrite::try_repeat_bounded(10, 3, countdown));
+    /// assert_eq!(Err("negative"), Rewrite::try_repeat_bounded(-1, 10, countdown));
+    /// ```
+    pub fn try_repeat_bounded<E>(
+        initial: T,
+        max: usize,
+        mut f: impl FnMut(T) -> Result<Self, E>,
+    ) -> Result<Result<T, T>, E> {
+        let mut val = initial;
+        for _ in 0..max {
+            match f(val)? {
+                Clean(done) => return Ok(Ok(done)),
+                Dirty(keep_going) => val = keep_going,
+            }
+        }
+        Ok(Err(val))
+    }
+
+    /// Applies a function and makes the result [`Dirty`] if `self` was already
+    /// dirty or became dirty as a result of the function.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    pub fn bind<U>(self, f: impl FnOnce(T) -> Rewrite<U>) -> Rewrite<U> {
+        match self {
+            Clean(clean) => f(clean),
+            Dirty(dirty) => Dirty(f(dirty).into_inner()),
+        }
+    }
+
+    /// Applies a function to a [`Clean`] value, returning [`Dirty`] values
+    /// unchanged.
+    ///
+    /// This can be used to try several rules in order until one of them fires.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let halve_even = |n: i32| if n % 2 == 0 { Dirty(n / 2) } else { Clean(n) };
+    ///
+    /// assert_eq!(Dirty(2), Clean(4).or_else(halve_even));
+    /// assert_eq!(Clean(3), Clean(3).or_else(halve_even));
+    /// assert_eq!(Dirty(4), Dirty(4).or_else(halve_even));
+    /// ```
+    pub fn or_else(self, f: impl FnOnce(T) -> Self) -> Self {
+        match self {
+            Clean(t) => f(t),
+            Dirty(t) => Dirty(t),
+        }
+    }
+
+    /// A version of [`bind`] that takes a fallible function.
+    ///
+    /// [`bind`]: Rewrite::bind
+    pub fn try_bind<E, U>(
+        self,
+        f: impl FnOnce(T) -> Result<Rewrite<U>, E>,
+    ) -> Result<Rewrite<U>, E> {
+        match self {
+            Clean(t) => f(t),
+            Dirty(t) => Ok(Dirty(f(t)?.into_inner())),
+        }
+    }
+
+    /// Downgrades a [`Dirty`] value to [`Clean`] if it is equal to `original`.
+    ///
+    /// This is useful when a transformation rebuilds a value without actually
+    /// changing it, which would otherwise keep a fixpoint loop going.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let original = vec![1, 2, 3];
+    /// let rebuilt: Rewrite<Vec<_>> = original.iter().map(|&n| Dirty(n)).collect();
+    ///
+    /// assert_eq!(Dirty(vec![1, 2, 3]), rebuilt);
+    /// assert_eq!(Clean(vec![1, 2, 3]), rebuilt.settle(&original));
+    /// assert_eq!(Dirty(vec![4]), Dirty(vec![4]).settle(&original));
+    /// ```
+    #[must_use]
+    pub fn settle(self, original: &T) -> Self
+    where
+        T: PartialEq,
+    {
+        match self {
+            Dirty(t) if t == *original => Clean(t),
+            _ => self,
+        }
+    }
+
+    /// Folds the values of a non-empty iterator of rewrites into a single
+    /// value, which is [`Dirty`] if any of the elements are.
+    ///
+    /// Returns `None` if the iterator is empty.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let sum = |a, b| a + b;
+    ///
+    /// assert_eq!(None, Rewrite::reduce([], sum));
+    /// assert_eq!(Some(Clean(1)), Rewrite::reduce([Clean(1)], sum));
+    /// assert_eq!(Some(Clean(6)), Rewrite::reduce([Clean(1), Clean(2), Clean(3)], sum));
+    /// assert_eq!(Some(Dirty(6)), Rewrite::reduce([Clean(1), Dirty(2), Clean(3)], sum));
+    /// ```
+    pub fn reduce(
+        iter: impl IntoIterator<Item = Self>,
+        f: impl FnMut(T, T) -> T,
+    ) -> Option<Self> {
+        let mut dirtiness = Dirtiness::CLEAN;
+        let reduced = iter
+            .into_iter()
+            .inspect(|item| dirtiness |= item.dirtiness())
+            .map(Self::into_inner)
+            .reduce(f)?;
+        Some(Self::with_dirtiness(reduced, dirtiness))
+    }
+
+    /// Returns the first [`Dirty`] element of an iterator, or the last element
+    /// if all of them are [`Clean`].
+    ///
+    /// This picks the result of the first rule that fired out of several rules
+    /// that were tried on the same node, in order. Elements after the first
+    /// [`Dirty`] one are not consumed. Returns `None` if the iterator is empty.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// assert_eq!(None, Rewrite::<i32>::first_dirty_or_last([]));
+    /// assert_eq!(
+    ///     Some(Clean(3)),
+    ///     Rewrite::first_dirty_or_last([Clean(1), Clean(2), Clean(3)]),
+    /// );
+    /// assert_eq!(
+    ///     Some(Dirty(2)),
+    ///     Rewrite::first_dirty_or_last([Clean(1), Dirty(2), Dirty(3), Clean(4)]),
+    /// );
+    /// ```
+    pub fn first_dirty_or_last(
+        iter: impl IntoIterator<Item = Self>,
+    ) -> Option<Self> {
+        let mut last = None;
+        for item in iter {
+            if item.is_dirty() {
+                return Some(item);
+            }
+            last = Some(item);
+        }
+        last
+    }
+
+    /// Rolls a [`Dirty`] value back to `original` if it doesn't satisfy
+    /// `valid`, making the result [`Clean`].
+    ///
+    /// [`Clean`] values, as well as [`Dirty`] values that do satisfy `valid`,
+    /// are returned unchanged.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let is_small = |n: &i32| *n < 10;
+    ///
+    /// assert_eq!(Clean(4), Dirty(40).recover(4, is_small)); // Rolled back
+    /// assert_eq!(Dirty(5), Dirty(5).recover(4, is_small)); // Kept
+    /// assert_eq!(Clean(40), Clean(40).recover(4, is_small)); // Kept
+    /// ```
+    #[must_use]
+    pub fn recover(self, original: T, valid: impl FnOnce(&T) -> bool) -> Self {
+        match self {
+            Dirty(t) if !valid(&t) => Clean(original),
+            _ => self,
+        }
+    }
+
+    /// Discards a [`Dirty`] value, replacing it with `default` as [`Clean`].
+    /// [`Clean`] values are returned unchanged.
+    ///
+    /// This can be used to undo a change.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Clean(0), Dirty(5).clean_or(0));
+    /// assert_eq!(Clean(5), Clean(5).clean_or(0));
+    /// ```
+    #[must_use]
+    pub fn clean_or(self, default: T) -> Self {
+        match self {
+            Clean(t) => Clean(t),
+            Dirty(_) => Clean(default),
+        }
+    }
+
+    /// Combines two rewrites into a rewrite of a pair, which is [`Dirty`] if
+    /// either of them are.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Clean((1, 'a')), Clean(1).zip(Clean('a')));
+    /// assert_eq!(Dirty((1, 'a')), Clean(1).zip(Dirty('a')));
+    /// assert_eq!(Dirty((1, 'a')), Dirty(1).zip(Dirty('a')));
+    /// ```
+    pub fn zip<U>(self, other: Rewrite<U>) -> Rewrite<(T, U)> {
+        let dirtiness = self.dirtiness() | other.dirtiness();
+        Rewrite::with_dirtiness(
+            (self.into_inner(), other.into_inner()),
+            dirtiness,
+        )
+    }
+
+    /// Combines two rewrites using one function for the values and another
+    /// for whether the result is [`Dirty`].
+    ///
+    /// `dirty_f` is given whether `self` and `other` are [`Dirty`], in that
+    /// order. [`zip`] is the special case that pairs up the values and uses
+    /// `||` for the dirtiness.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    /// [`zip`]: Rewrite::zip
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let or = |a, b| a || b;
+    /// let and = |a, b| a && b;
+    /// let xor = |a, b| a != b;
+    /// let add = |a, b| a + b;
+    ///
+    /// assert_eq!(Dirty(3), Clean(1).combine(Dirty(2), add, or));
+    /// assert_eq!(Clean(3), Clean(1).combine(Clean(2), add, or));
+    ///
+    /// assert_eq!(Clean(3), Clean(1).combine(Dirty(2), add, and));
+    /// assert_eq!(Dirty(3), Dirty(1).combine(Dirty(2), add, and));
+    ///
+    /// assert_eq!(Dirty(3), Dirty(1).combine(Clean(2), add, xor));
+    /// assert_eq!(Clean(3), Dirty(1).combine(Dirty(2), add, xor));
+    /// ```
+    pub fn combine<U, V>(
+        self,
+        other: Rewrite<U>,
+        value_f: impl FnOnce(T, U) -> V,
+        dirty_f: impl FnOnce(bool, bool) -> bool,
+    ) -> Rewrite<V> {
+        let dirty = dirty_f(self.is_dirty(), other.is_dirty());
+        let value = value_f(self.into_inner(), other.into_inner());
+        Rewrite::with_dirtiness(value, Dirtiness::from(dirty))
+    }
+
+    /// Builds a value out of two rewritten parts, which is [`Dirty`] if either
+    /// of them are.
+    ///
+    /// This is the same as [`zip`] followed by [`map`], and is handy for
+    /// rebuilding nodes with two branches.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    /// [`zip`]: Rewrite::zip
+    /// [`map`]: Rewrite::map
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// #[derive(Debug, PartialEq)]
+    /// struct Add(i32, i32);
+    ///
+    /// assert_eq!(Clean(Add(1, 2)), Rewrite::lift2(Clean(1), Clean(2), Add));
+    /// assert_eq!(Dirty(Add(1, 2)), Rewrite::lift2(Clean(1), Dirty(2), Add));
+    /// ```
+    pub fn lift2<A, B>(
+        a: Rewrite<A>,
+        b: Rewrite<B>,
+        f: impl FnOnce(A, B) -> T,
+    ) -> Self {
+        a.zip(b).map(|(a, b)| f(a, b))
+    }
+
+    /// Builds a value out of three rewritten parts, which is [`Dirty`] if any
+    /// of them are.
+    ///
+    /// This is like [`lift2`], but for nodes with three branches.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    /// [`lift2`]: Rewrite::lift2
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// #[derive(Debug, PartialEq)]
+    /// struct If(bool, i32, i32);
+    ///
+    /// assert_eq!(
+    ///     Clean(If(true, 1, 2)),
+    ///     Rewrite::lift3(Clean(true), Clean(1), Clean(2), If),
+    /// );
+    /// assert_eq!(
+    ///     Dirty(If(true, 1, 2)),
+    ///     Rewrite::lift3(Dirty(true), Clean(1), Clean(2), If),
+    /// );
+    /// ```
+    pub fn lift3<A, B, C>(
+        a: Rewrite<A>,
+        b: Rewrite<B>,
+        c: Rewrite<C>,
+        f: impl FnOnce(A, B, C) -> T,
+    ) -> Self {
+        a.zip(b).zip(c).map(|((a, b), c)| f(a, b, c))
+    }
+
+    /// Combines an accumulator with a rewritten item, which is [`Dirty`] if
+    /// either of them are.
+    ///
+    /// This is the step function of a left fold over rewrites, and can be
+    /// passed to [`Iterator::fold`] almost as is.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let sum = |items: Vec<_>| {
+    ///     items
+    ///         .into_iter()
+    ///         .fold(Clean(0), |sum, item| sum.fold_in(item, |a, b| a + b))
+    /// };
+    ///
+    /// assert_eq!(Clean(6), sum(vec![Clean(1), Clean(2), Clean(3)]));
+    /// assert_eq!(Dirty(6), sum(vec![Clean(1), Dirty(2), Clean(3)]));
+    /// assert_eq!(Clean(0), sum(vec![]));
+    /// ```
+    pub fn fold_in<U>(
+        self,
+        item: Rewrite<U>,
+        f: impl FnOnce(T, U) -> T,
+    ) -> Self {
+        Self::lift2(self, item, f)
+    }
+
+    /// A version of [`zip`] for rewrites that are expected to be either both
+    /// [`Clean`] or both [`Dirty`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if debug assertions are enabled and exactly one of the rewrites
+    /// is [`Dirty`]. Otherwise this behaves exactly like [`zip`].
+    ///
+    /// [`zip`]: Rewrite::zip
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Clean((1, 'a')), Clean(1).zip_eq(Clean('a')));
+    /// assert_eq!(Dirty((1, 'a')), Dirty(1).zip_eq(Dirty('a')));
+    ///
+    /// let mismatched = std::panic::catch_unwind(|| Clean(1).zip_eq(Dirty('a')));
+    /// match mismatched {
+    ///     Err(_) => {} // With debug assertions
+    ///     Ok(zipped) => assert_eq!(Dirty((1, 'a')), zipped), // Without
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn zip_eq<U>(self, other: Rewrite<U>) -> Rewrite<(T, U)> {
+        debug_assert_eq!(
+            self.dirtiness(),
+            other.dirtiness(),
+            "`zip_eq` called with rewrites of different dirtiness",
+        );
+        self.zip(other)
+    }
+
+    /// Calls a function with a reference to the inner value, without changing
+    /// whether it's [`Clean`] or [`Dirty`].
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Clean;
+    /// let mut seen = 0;
+    /// assert_eq!(&Clean(1), Clean(1).tap(|&n| seen = n));
+    /// assert_eq!(1, seen);
+    /// ```
+    pub fn tap(&self, f: impl FnOnce(&T)) -> &Self {
+        f(self.inner_ref());
+        self
+    }
+
+    /// Mutates the inner value in place and marks it as [`Dirty`], since it
+    /// has been touched.
+    ///
+    /// This takes `self` by value because the variant can't be changed
+    /// through a mutable reference without replacing the inner value.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Dirty(vec![1, 2]), Clean(vec![1]).tap_mut(|v| v.push(2)));
+    /// assert_eq!(Dirty(vec![1, 2]), Dirty(vec![1]).tap_mut(|v| v.push(2)));
+    /// ```
+    #[must_use]
+    pub fn tap_mut(self, f: impl FnOnce(&mut T)) -> Self {
+        let mut t = self.into_inner();
+        f(&mut t);
+        Dirty(t)
+    }
+
+    /// Returns the value if it's [`Clean`], or `err` if it's [`Dirty`].
+    ///
+    /// This is useful for checking that a value that should already be in
+    /// normal form is left untouched by a normalization step.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Ok(1), Clean(1).require_clean("changed"));
+    /// assert_eq!(Err("changed"), Dirty(1).require_clean("changed"));
+    /// ```
+    pub fn require_clean<E>(self, err: E) -> Result<T, E> {
+        match self {
+            Clean(t) => Ok(t),
+            Dirty(_) => Err(err),
+        }
+    }
+
+    /// A version of [`require_clean`] that computes the error from the
+    /// [`Dirty`] value.
+    ///
+    /// [`require_clean`]: Rewrite::require_clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let changed_to = |n| format!("changed to {n}");
+    /// assert_eq!(Ok(1), Clean(1).require_clean_with(changed_to));
+    /// assert_eq!(Err("changed to 2".to_owned()), Dirty(2).require_clean_with(changed_to));
+    /// ```
+    pub fn require_clean_with<E>(
+        self,
+        err: impl FnOnce(T) -> E,
+    ) -> Result<T, E> {
+        match self {
+            Clean(t) => Ok(t),
+            Dirty(t) => Err(err(t)),
+        }
+    }
+
+    /// Calls a function with a reference to the inner value, then returns
+    /// `self` unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let mut seen = Vec::new();
+    /// let rewrites = [Clean(1), Dirty(2)].map(|r| r.inspect(|&n| seen.push(n)));
+    /// assert_eq!([Clean(1), Dirty(2)], rewrites);
+    /// assert_eq!(vec![1, 2], seen);
+    /// ```
+    pub fn inspect(self, f: impl FnOnce(&T)) -> Self {
+        f(self.inner_ref());
+        self
+    }
+
+    /// Calls a function with a reference to the inner value if it's
+    /// [`Clean`], then returns `self` unchanged.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let mut skipped = Vec::new();
+    /// let rewrites = [Clean(1), Dirty(2)].map(|r| r.inspect_clean(|&n| skipped.push(n)));
+    /// assert_eq!([Clean(1), Dirty(2)], rewrites);
+    /// assert_eq!(vec![1], skipped);
+    /// ```
+    pub fn inspect_clean(self, f: impl FnOnce(&T)) -> Self {
+        self.on_variant(f, |_| {})
+    }
+
+    /// Calls a function with a reference to the inner value if it's
+    /// [`Dirty`], then returns `self` unchanged.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let mut rewrote = Vec::new();
+    /// let rewrites = [Clean(1), Dirty(2)].map(|r| r.inspect_dirty(|&n| rewrote.push(n)));
+    /// assert_eq!([Clean(1), Dirty(2)], rewrites);
+    /// assert_eq!(vec![2], rewrote);
+    /// ```
+    pub fn inspect_dirty(self, f: impl FnOnce(&T)) -> Self {
+        self.on_variant(|_| {}, f)
+    }
+
+    /// Increments `counter` if `self` is [`Dirty`], then returns `self`
+    /// unchanged.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let mut changes = 0;
+    /// let rewrites = [Dirty(1), Clean(2), Dirty(3)].map(|r| r.count_dirty(&mut changes));
+    /// assert_eq!([Dirty(1), Clean(2), Dirty(3)], rewrites);
+    /// assert_eq!(2, changes);
+    /// ```
+    pub fn count_dirty(self, counter: &mut usize) -> Self {
+        self.inspect_dirty(|_| *counter += 1)
+    }
+
+    /// Calls one of two functions with a reference to the inner value
+    /// depending on whether it's [`Clean`] or [`Dirty`], then returns `self`
+    /// unchanged.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let (mut skipped, mut rewrote) = (0, 0);
+    /// for rewrite in [Clean(1), Dirty(2), Dirty(3)] {
+    ///     rewrite.on_variant(|_| skipped += 1, |_| rewrote += 1);
+    /// }
+    /// assert_eq!((1, 2), (skipped, rewrote));
+    /// ```
+    pub fn on_variant(
+        self,
+        clean_f: impl FnOnce(&T),
+        dirty_f: impl FnOnce(&T),
+    ) -> Self {
+        match &self {
+            Clean(t) => clean_f(t),
+            Dirty(t) => dirty_f(t),
+        }
+        self
+    }
+
+    /// Discards the inner value, keeping only whether it's [`Clean`] or
+    /// [`Dirty`].
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Dirty(()), Dirty(5).void());
+    /// assert_eq!(Clean(()), Clean(5).void());
+    /// ```
+    pub fn void(self) -> Rewrite<()> {
+        self.map(|_| ())
+    }
+
+    /// Turns an array of rewrites into a rewrite of an array, which is
+    /// [`Dirty`] if any of the elements are.
+    ///
+    /// This is like collecting into a `Rewrite<C>`, but without needing a
+    /// growable collection. The inverse is [`distribute`].
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    /// [`distribute`]: Rewrite::distribute
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// assert_eq!(Clean([]), Rewrite::<i32>::sequence_array([]));
+    /// assert_eq!(Clean([1, 2, 3]), Rewrite::sequence_array([Clean(1), Clean(2), Clean(3)]));
+    /// assert_eq!(Dirty([1, 2, 3]), Rewrite::sequence_array([Clean(1), Dirty(2), Clean(3)]));
+    /// ```
+    pub fn sequence_array<const N: usize>(array: [Self; N]) -> Rewrite<[T; N]> {
+        let dirtiness =
+            array.iter().fold(Dirtiness::CLEAN, |dirtiness, item| {
+                dirtiness | item.dirtiness()
+            });
+        Rewrite::with_dirtiness(array.map(Self::into_inner), dirtiness)
+    }
+
+    /// Asserts that `value` is a fixpoint of `f`, meaning that applying `f` to
+    /// it once more returns [`Clean`].
+    ///
+    /// This is mostly useful in tests of rewrite rules, to check that the
+    /// result of normalizing something really is normalized.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f(value)` is [`Dirty`], showing the value it produced.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// fn halve(n: i32) -> Rewrite<i32> {
+    ///     if n % 2 == 0 && n != 0 { Dirty(n / 2) } else { Clean(n) }
+    /// }
+    ///
+    /// Rewrite::assert_fixpoint(3, halve);
+    /// ```
+    ///
+    /// ```should_panic
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// # fn halve(n: i32) -> Rewrite<i32> {
+    /// #     if n % 2 == 0 && n != 0 { Dirty(n / 2) } else { Clean(n) }
+    /// # }
+    /// Rewrite::assert_fixpoint(12, halve);
+    /// ```
+    #[track_caller]
+    pub fn assert_fixpoint(value: T, f: impl FnOnce(T) -> Self)
+    where
+        T: Debug,
+    {
+        if let Dirty(rewritten) = f(value) {
+            panic!(
+                "value is not a fixpoint, it was rewritten to {rewritten:?}"
+            );
+        }
+    }
+
+    /// Runs a check on a [`Dirty`] value before accepting it, returning `Err`
+    /// if the check fails.
+    ///
+    /// [`Clean`] values are passed through without being checked. This can be
+    /// used to enforce postconditions on the output of a rule.
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let non_negative = |n: &i32| if *n < 0 { Err("negative") } else { Ok(()) };
+    ///
+    /// assert_eq!(Ok(Dirty(1)), Dirty(1).validate_dirty(non_negative));
+    /// assert_eq!(Err("negative"), Dirty(-1).validate_dirty(non_negative));
+    /// assert_eq!(Ok(Clean(-1)), Clean(-1).validate_dirty(non_negative));
+    /// ```
+    pub fn validate_dirty<E>(
+        self,
+        check: impl FnOnce(&T) -> Result<(), E>,
+    ) -> Result<Self, E> {
+        if let Dirty(t) = &self {
+            check(t)?;
+        }
+        Ok(self)
+    }
+
+    /// Moves the inner value into a [`Box`], keeping the variant.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// [`Box`]: alloc::boxed::Box
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Clean(Box::new(1)), Clean(1).boxed());
+    /// assert_eq!(Dirty(Box::new(1)), Dirty(1).boxed());
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn boxed(self) -> Rewrite<Box<T>> {
+        self.map(Box::new)
+    }
+
+    /// Converts the inner value with [`TryInto`], keeping the variant if the
+    /// conversion succeeds.
+    ///
+    /// [`TryInto`]: core::convert::TryInto
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let small: Rewrite<i64> = Dirty(42);
+    /// assert_eq!(Ok(Dirty(42_i32)), small.try_into_inner());
+    ///
+    /// let large: Rewrite<i64> = Clean(1 << 40);
+    /// assert!(large.try_into_inner::<i32>().is_err());
+    /// ```
+    pub fn try_into_inner<U>(self) -> Result<Rewrite<U>, T::Error>
+    where
+        T: TryInto<U>,
+    {
+        self.map(T::try_into).transpose_result()
+    }
+
+    /// Overwrites `target` with a clone of the rewrite, reusing the storage of
+    /// its inner value if both are the same variant.
+    ///
+    /// This is useful for avoiding allocations in loops that keep a scratch
+    /// [`Rewrite`] around.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// let mut scratch = Dirty(Vec::with_capacity(16));
+    ///
+    /// Dirty(vec![1, 2, 3]).clone_into(&mut scratch);
+    /// assert_eq!(Dirty(vec![1, 2, 3]), scratch);
+    /// assert!(scratch.inner_ref().capacity() >= 16);
+    ///
+    /// Clean(vec![4]).clone_into(&mut scratch);
+    /// assert_eq!(Clean(vec![4]), scratch);
+    /// ```
+    pub fn clone_into(&self, target: &mut Self)
+    where
+        T: Clone,
+    {
+        match (self, target) {
+            (Clean(source), Clean(target)) | (Dirty(source), Dirty(target)) => {
+                target.clone_from(source);
+            }
+            (source, target) => *target = source.clone(),
+        }
+    }
+}
+
+impl<T> Rewrite<&T> {
+    /// Creates a new [`Rewrite`] by copying the inner value.
+    ///
+    /// [`Rewrite`]: Rewrite
+    #[must_use]
+    pub const fn copied(self) -> Rewrite<T>
+    where
+        T: Copy,
+    {
+        match self {
+            Clean(t) => Clean(*t),
+            Dirty(t) => Dirty(*t),
+        }
+    }
+
+    /// Creates a new [`Rewrite`] by cloning the inner value.
+    ///
+    /// [`Rewrite`]: Rewrite
+    pub fn cloned(self) -> Rewrite<T>
+    where
+        T: Clone,
+    {
+        self.map(Clone::clone)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Rewrite<Box<T>> {
+    /// Moves the inner value out of its [`Box`], keeping the variant.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// [`Box`]: alloc::boxed::Box
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Clean(1), Clean(Box::new(1)).unbox());
+    /// assert_eq!(Dirty(1), Dirty(Box::new(1)).unbox());
+    /// ```
+    pub fn unbox(self) -> Rewrite<T> {
+        self.map(|boxed| *boxed)
+    }
+}
+
+impl<T, const N: usize> Rewrite<[T; N]> {
+    /// Turns a rewrite of an array into an array of rewrites, each of which is
+    /// [`Clean`] or [`Dirty`] like `self`.
+    ///
+    /// This is the inverse of [`sequence_array`].
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    /// [`sequence_array`]: Rewrite::sequence_array
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!([Clean(1), Clean(2)], Clean([1, 2]).distribute());
+    /// assert_eq!([Dirty(1), Dirty(2)], Dirty([1, 2]).distribute());
+    /// ```
+    pub fn distribute(self) -> [Rewrite<T>; N] {
+        let dirtiness = self.dirtiness();
+        self.into_inner()
+            .map(|item| Rewrite::with_dirtiness(item, dirtiness))
+    }
+}
+
+impl Rewrite<()> {
+    /// Returns [`DIRTY`] if any of the rewrites are [`Dirty`], or [`CLEAN`]
+    /// otherwise.
+    ///
+    /// [`CLEAN`]: crate::Dirtiness::CLEAN
+    /// [`DIRTY`]: crate::Dirtiness::DIRTY
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, Dirtiness};
+    /// assert_eq!(Dirtiness::CLEAN, Rewrite::dirtiness_of([]));
+    /// assert_eq!(Dirtiness::CLEAN, Rewrite::dirtiness_of([Clean(()), Clean(())]));
+    /// assert_eq!(Dirtiness::DIRTY, Rewrite::dirtiness_of([Clean(()), Dirty(())]));
+    /// ```
+    pub fn dirtiness_of(items: impl IntoIterator<Item = Self>) -> Dirtiness {
+        items.into_iter().fold(Dirtiness::CLEAN, |dirtiness, item| {
+            dirtiness | item.dirtiness()
+        })
+    }
+}
+
+impl<T> Rewrite<Rewrite<T>> {
+    /// Swaps two layers of [`Rewrite`].
+    ///
+    /// [`Rewrite`]: Rewrite
     ///
     /// # Examples
     ///
@@ -225,6 +1609,32 @@ impl<T> Rewrite<Rewrite<T>> {
     }
 }
 
+impl<T> Rewrite<Option<T>> {
+    /// Unwraps the inner [`Option`], substituting `default` if it's `None`.
+    ///
+    /// Filling in a missing value counts as a change, so the result is always
+    /// [`Dirty`] in that case. A `Some` value keeps its original variant.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Clean, Dirty};
+    /// assert_eq!(Clean(1), Clean(Some(1)).or_default_dirty(0));
+    /// assert_eq!(Dirty(1), Dirty(Some(1)).or_default_dirty(0));
+    /// assert_eq!(Dirty(0), Clean(None).or_default_dirty(0));
+    /// assert_eq!(Dirty(0), Dirty(None).or_default_dirty(0));
+    /// ```
+    pub fn or_default_dirty(self, default: T) -> Rewrite<T> {
+        match self {
+            Clean(Some(t)) => Clean(t),
+            Dirty(Some(t)) => Dirty(t),
+            Clean(None) | Dirty(None) => Dirty(default),
+        }
+    }
+}
+
 impl<T, E> Rewrite<Result<T, E>> {
     /// Converts `Rewrite<Result<T, E>>` into `Result<Rewrite<T, E>>`.
     pub fn transpose_result(self) -> Result<Rewrite<T>, E> {
@@ -233,6 +1643,124 @@ impl<T, E> Rewrite<Result<T, E>> {
             Dirty(t) => Ok(Dirty(t?)),
         }
     }
+
+    /// Maps the error of the inner [`Result`], leaving an `Ok` value untouched
+    /// and keeping the variant.
+    ///
+    /// [`Result`]: core::result::Result
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let ok: Rewrite<Result<i32, i32>> = Clean(Ok(1));
+    /// assert_eq!(Clean(Ok(1)), ok.map_err(|e| e.to_string()));
+    /// let ok: Rewrite<Result<i32, i32>> = Dirty(Ok(1));
+    /// assert_eq!(Dirty(Ok(1)), ok.map_err(|e| e.to_string()));
+    ///
+    /// let err: Rewrite<Result<i32, i32>> = Clean(Err(2));
+    /// assert_eq!(Clean(Err("2".to_owned())), err.map_err(|e| e.to_string()));
+    /// let err: Rewrite<Result<i32, i32>> = Dirty(Err(2));
+    /// assert_eq!(Dirty(Err("2".to_owned())), err.map_err(|e| e.to_string()));
+    /// ```
+    pub fn map_err<F>(self, f: impl FnOnce(E) -> F) -> Rewrite<Result<T, F>> {
+        self.map(|result| result.map_err(f))
+    }
+
+    /// Calls a function with a reference to the error of the inner [`Result`]
+    /// if there is one, then returns `self` unchanged.
+    ///
+    /// [`Result`]: core::result::Result
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let mut errors = Vec::new();
+    /// let results: [Rewrite<Result<i32, &str>>; 3] = [Clean(Ok(1)), Dirty(Err("a")), Clean(Err("b"))];
+    /// let results = results.map(|r| r.inspect_err(|&e| errors.push(e)));
+    ///
+    /// assert_eq!([Clean(Ok(1)), Dirty(Err("a")), Clean(Err("b"))], results);
+    /// assert_eq!(vec!["a", "b"], errors);
+    /// ```
+    pub fn inspect_err(self, f: impl FnOnce(&E)) -> Self {
+        if let Err(e) = self.inner_ref() {
+            f(e);
+        }
+        self
+    }
+
+    /// Combines two fallible rewrites into a fallible rewrite of a pair.
+    ///
+    /// The result is [`Dirty`] if either of the rewrites are, regardless of
+    /// whether they succeeded. If both of them failed, the error from `self`
+    /// is kept and the one from `other` is dropped.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// let ok = |n| -> Rewrite<Result<i32, char>> { Clean(Ok(n)) };
+    ///
+    /// assert_eq!(Clean(Ok((1, 2))), ok(1).try_zip(ok(2)));
+    /// assert_eq!(Dirty(Ok((1, 2))), ok(1).try_zip(Dirty(Ok(2))));
+    /// assert_eq!(Dirty(Err('b')), ok(1).try_zip(Dirty(Err::<i32, _>('b'))));
+    /// assert_eq!(Clean(Err('a')), Clean(Err::<i32, _>('a')).try_zip(Clean(Err::<i32, _>('b'))));
+    /// ```
+    pub fn try_zip<U>(
+        self,
+        other: Rewrite<Result<U, E>>,
+    ) -> Rewrite<Result<(T, U), E>> {
+        self.zip(other).map(|(a, b)| Ok((a?, b?)))
+    }
+}
+
+impl<T, E> Rewrite<Result<Option<T>, E>> {
+    /// Converts `Rewrite<Result<Option<T>, E>>` into
+    /// `Result<Option<Rewrite<T>>, E>`.
+    ///
+    /// The six possible shapes are mapped as follows:
+    ///
+    /// | Input                | Output                  |
+    /// |----------------------|-------------------------|
+    /// | `Clean(Err(e))`      | `Err(e)`                |
+    /// | `Dirty(Err(e))`      | `Err(e)`                |
+    /// | `Clean(Ok(None))`    | `Ok(None)`              |
+    /// | `Dirty(Ok(None))`    | `Ok(None)`              |
+    /// | `Clean(Ok(Some(t)))` | `Ok(Some(Clean(t)))`    |
+    /// | `Dirty(Ok(Some(t)))` | `Ok(Some(Dirty(t)))`    |
+    ///
+    /// Note that whether the value was [`Dirty`] is lost when there is no
+    /// value to attach it to.
+    ///
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty};
+    /// type Step = Rewrite<Result<Option<i32>, &'static str>>;
+    ///
+    /// let failed: Step = Dirty(Err("oops"));
+    /// assert_eq!(Err("oops"), failed.try_transpose_option());
+    ///
+    /// let deleted: Step = Dirty(Ok(None));
+    /// assert_eq!(Ok(None), deleted.try_transpose_option());
+    ///
+    /// let kept: Step = Clean(Ok(Some(1)));
+    /// assert_eq!(Ok(Some(Clean(1))), kept.try_transpose_option());
+    ///
+    /// let changed: Step = Dirty(Ok(Some(2)));
+    /// assert_eq!(Ok(Some(Dirty(2))), changed.try_transpose_option());
+    /// ```
+    pub fn try_transpose_option(self) -> Result<Option<Rewrite<T>>, E> {
+        Ok(match self {
+            Clean(t) => t?.map(Clean),
+            Dirty(t) => t?.map(Dirty),
+        })
+    }
 }
 
 /// Makes the entire collection [`Dirty`] if any of the elements are.
@@ -243,17 +1771,69 @@ where
     C: FromIterator<T>,
 {
     fn from_iter<I: IntoIterator<Item = Rewrite<T>>>(iter: I) -> Self {
-        let mut is_dirty = false;
+        let mut dirtiness = Dirtiness::CLEAN;
         let collected = iter
             .into_iter()
-            .inspect(|item| is_dirty |= item.is_dirty())
+            .inspect(|item| dirtiness |= item.dirtiness())
             .map(Rewrite::into_inner)
             .collect();
-        if is_dirty {
-            Dirty(collected)
-        } else {
-            Clean(collected)
-        }
+        Self::with_dirtiness(collected, dirtiness)
+    }
+}
+
+/// Maps [`Dirty`] to [`Break`] and [`Clean`] to [`Continue`], like
+/// [`Rewrite::into_control_flow`].
+///
+/// [`Clean`]: Rewrite::Clean
+/// [`Dirty`]: Rewrite::Dirty
+/// [`Break`]: core::ops::ControlFlow::Break
+/// [`Continue`]: core::ops::ControlFlow::Continue
+impl<T> From<Rewrite<T>> for ControlFlow<T, T> {
+    fn from(rewrite: Rewrite<T>) -> Self {
+        rewrite.into_control_flow()
+    }
+}
+
+/// Keeps the value only if it's [`Dirty`], like [`Rewrite::dirty`].
+///
+/// A transformation that changed something is the productive case, so that is
+/// what maps to `Some`. This is the same convention as that of
+/// [`Rewrite::into_control_flow`].
+///
+/// [`Dirty`]: Rewrite::Dirty
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{Clean, Dirty};
+/// assert_eq!(Some(1), Option::<i32>::from(Dirty(1)));
+/// assert_eq!(None, Option::<i32>::from(Clean(1)));
+/// ```
+impl<T> From<Rewrite<T>> for Option<T> {
+    fn from(rewrite: Rewrite<T>) -> Self {
+        rewrite.dirty()
+    }
+}
+
+/// Formats the value prefixed by `[clean]` or `[dirty]` and a space.
+///
+/// Formatting options such as width and precision are passed on to the value.
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{Clean, Dirty};
+/// assert_eq!("[clean] 1", Clean(1).to_string());
+/// assert_eq!("[dirty] x + 1", Dirty("x + 1").to_string());
+/// assert_eq!("[dirty] 1.50", format!("{:.2}", Dirty(1.5)));
+/// ```
+impl<T: Display> Display for Rewrite<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Clean(_) => "[clean] ",
+            Dirty(_) => "[dirty] ",
+        })?;
+        self.inner_ref().fmt(f)
     }
 }
 
@@ -265,3 +1845,63 @@ impl<T> Bind<Rewrite<Self>> for T {
         wrapped.bind(f)
     }
 }
+
+/// Extension methods for the effect stack consisting of both [`Result`] and
+/// [`Rewrite`].
+///
+/// [`Result`]: core::result::Result
+pub trait ResultRewriteExt<T, E> {
+    /// Applies a fallible rewriting function to the value inside an `Ok`,
+    /// short-circuiting on errors.
+    ///
+    /// This is like the [`Bind`] impl for `Result<Rewrite<T>, E>`, except that
+    /// the function may change the type of the value. The result is [`Dirty`]
+    /// if either `self` or the result of the function is.
+    ///
+    /// [`Bind`]: crate::Bind
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, ResultRewriteExt};
+    /// fn parse(s: &str) -> Result<Rewrite<i32>, String> {
+    ///     s.trim()
+    ///         .parse()
+    ///         .map(|n| if s.len() == s.trim().len() { Clean(n) } else { Dirty(n) })
+    ///         .map_err(|_| format!("not a number: {s:?}"))
+    /// }
+    ///
+    /// fn halve(n: i32) -> Result<Rewrite<i32>, String> {
+    ///     match n % 2 {
+    ///         0 => Ok(Dirty(n / 2)),
+    ///         _ => Ok(Clean(n)),
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(Ok(Clean(3)), parse("3").and_then_rewrite(halve));
+    /// assert_eq!(Ok(Dirty(3)), parse(" 3").and_then_rewrite(halve));
+    /// assert_eq!(Ok(Dirty(2)), parse("4").and_then_rewrite(halve));
+    ///
+    /// let mut called = false;
+    /// let failed = parse("four").and_then_rewrite(|n| {
+    ///     called = true;
+    ///     halve(n)
+    /// });
+    /// assert_eq!(Err("not a number: \"four\"".to_owned()), failed);
+    /// assert!(!called);
+    /// ```
+    fn and_then_rewrite<U>(
+        self,
+        f: impl FnOnce(T) -> Result<Rewrite<U>, E>,
+    ) -> Result<Rewrite<U>, E>;
+}
+
+impl<T, E> ResultRewriteExt<T, E> for Result<Rewrite<T>, E> {
+    fn and_then_rewrite<U>(
+        self,
+        f: impl FnOnce(T) -> Result<Rewrite<U>, E>,
+    ) -> Result<Rewrite<U>, E> {
+        self?.try_bind(f)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::Rewrite;
+
+/// Trait for rewrite rules that can be applied to a single node.
+///
+/// This is implemented for all closures of the right type, but can also be
+/// implemented by hand for rules that need to be named or stored as trait
+/// objects.
+pub trait Rule<S> {
+    /// Applies the rule to a node, returning [`Dirty`] if it fired.
+    ///
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    fn apply(&mut self, node: S) -> Rewrite<S>;
+}
+
+impl<S, F: FnMut(S) -> Rewrite<S>> Rule<S> for F {
+    fn apply(&mut self, node: S) -> Rewrite<S> {
+        self(node)
+    }
+}
+
+/// A [`Rule`] made from a function that is given mutable access to some state
+/// along with each node.
+///
+/// This is equivalent to a closure that captures the state, but keeps the
+/// state accessible from the outside and gives the rule a nameable type.
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{Rewrite, Clean, Dirty, FnRule, Rule, TreeWalk};
+/// #[derive(Debug, PartialEq)]
+/// enum Expr {
+///     Hole,
+///     Id(u32),
+///     Pair(Box<Expr>, Box<Expr>),
+/// }
+/// # use Expr::*;
+/// # impl TreeWalk<Rewrite<Self>> for Expr {
+/// #     fn each_branch(
+/// #         self,
+/// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+/// #     ) -> Rewrite<Self> {
+/// #         match self {
+/// #             Pair(a, b) => f(*a)
+/// #                 .bind(|a| f(*b).map(|b| Pair(Box::new(a), Box::new(b)))),
+/// #             _ => Clean(self),
+/// #         }
+/// #     }
+/// # }
+///
+/// let mut number_holes = FnRule::new(0, |next: &mut u32, expr| match expr {
+///     Hole => {
+///         *next += 1;
+///         Dirty(Id(*next - 1))
+///     }
+///     _ => Clean(expr),
+/// });
+///
+/// let tree = Pair(Box::new(Hole), Box::new(Pair(Box::new(Hole), Box::new(Hole))));
+/// let numbered = tree.bottom_up(|expr| number_holes.apply(expr));
+///
+/// assert_eq!(
+///     Dirty(Pair(Box::new(Id(0)), Box::new(Pair(Box::new(Id(1)), Box::new(Id(2)))))),
+///     numbered,
+/// );
+/// assert_eq!(3, number_holes.state);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct FnRule<St, F> {
+    /// The state that is passed to the function.
+    pub state: St,
+    f: F,
+}
+
+impl<St, F> FnRule<St, F> {
+    /// Creates a rule that calls `f` with `state` and the node.
+    pub const fn new(state: St, f: F) -> Self {
+        Self { state, f }
+    }
+}
+
+impl<S, St, F: FnMut(&mut St, S) -> Rewrite<S>> Rule<S> for FnRule<St, F> {
+    fn apply(&mut self, node: S) -> Rewrite<S> {
+        (self.f)(&mut self.state, node)
+    }
+}
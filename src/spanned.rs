@@ -0,0 +1,71 @@
+use crate::Rewrite;
+
+/// A value paired with the span of source code that it came from.
+///
+/// The span type `S` is left up to the user. Transformations of the value
+/// keep the original span unless they explicitly replace it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Spanned<T, S> {
+    /// The value itself.
+    pub node: T,
+    /// The span of source code that the value came from.
+    pub span: S,
+}
+
+impl<T, S> Spanned<T, S> {
+    /// Pairs a value with a span.
+    pub const fn new(node: T, span: S) -> Self {
+        Self { node, span }
+    }
+
+    /// Maps a function over the value, keeping the span.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U, S> {
+        Spanned {
+            node: f(self.node),
+            span: self.span,
+        }
+    }
+
+    /// Applies a rewriting function to the value, keeping the span.
+    ///
+    /// This turns a rule for `T` into a rule for `Spanned<T, S>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, Spanned};
+    /// let halve_even = |n: i32| if n % 2 == 0 { Dirty(n / 2) } else { Clean(n) };
+    ///
+    /// assert_eq!(
+    ///     Dirty(Spanned::new(2, 10..11)),
+    ///     Spanned::new(4, 10..11).rewrite(halve_even),
+    /// );
+    /// assert_eq!(
+    ///     Clean(Spanned::new(3, 10..11)),
+    ///     Spanned::new(3, 10..11).rewrite(halve_even),
+    /// );
+    /// ```
+    pub fn rewrite(self, f: impl FnOnce(T) -> Rewrite<T>) -> Rewrite<Self> {
+        let span = self.span;
+        f(self.node).map(|node| Self { node, span })
+    }
+}
+
+impl<T, S> Rewrite<Spanned<T, S>> {
+    /// Maps a function over the spanned value, keeping both the span and
+    /// whether it's [`Clean`] or [`Dirty`].
+    ///
+    /// [`Clean`]: Rewrite::Clean
+    /// [`Dirty`]: Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Dirty, Spanned};
+    /// let spanned = Dirty(Spanned::new(1, "line 3"));
+    /// assert_eq!(Dirty(Spanned::new("1".to_owned(), "line 3")), spanned.map_node(|n| n.to_string()));
+    /// ```
+    pub fn map_node<U>(self, f: impl FnOnce(T) -> U) -> Rewrite<Spanned<U, S>> {
+        self.map(|spanned| spanned.map(f))
+    }
+}
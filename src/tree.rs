@@ -1,4 +1,13 @@
-use crate::Bind;
+#[cfg(feature = "alloc")]
+use crate::Rule;
+use crate::{Bind, Clean, Dirty, Rewrite};
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use core::hash::Hash;
+use core::{cell::RefCell, hash::Hasher, ops::ControlFlow};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
 /// Trait for tree-like structures that can be recursively transformed with
 /// effectful computations.
@@ -19,4 +28,3473 @@ pub trait TreeWalk<FS>: Bind<FS> {
         }
         go(self, &mut f)
     }
+
+    /// Applies an effectful function to every node of a tree, including the
+    /// root itself, in a top-down manner.
+    ///
+    /// The function is applied to a node before its branches, and the
+    /// branches that get visited are those of the node that the function
+    /// returned.
+    fn top_down(self, mut f: impl FnMut(Self) -> FS) -> FS {
+        fn go<S: TreeWalk<FS>, FS>(
+            branch: S,
+            f: &mut impl FnMut(S) -> FS,
+        ) -> FS {
+            Bind::bind_mut(f(branch), |branch: S| {
+                branch.each_branch(|branch| go(branch, f))
+            })
+        }
+        go(self, &mut f)
+    }
+
+    /// A version of [`bottom_up`] that also passes the parent of each node to
+    /// the function, or `None` for the root.
+    ///
+    /// Since the parent is consumed and rebuilt while its branches are being
+    /// transformed, the function sees the parent as it was in a clone of the
+    /// whole tree that is taken up front. That is, the parent is always in its
+    /// original, untransformed state. The clone is walked alongside the tree
+    /// using [`children`], which must yield branches in the same order that
+    /// [`each_branch`] visits them. Cloning the tree once makes this more
+    /// expensive than [`bottom_up`], but only by a constant factor.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`children`]: Children::children
+    /// [`each_branch`]: TreeWalk::each_branch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Children, TreeWalk};
+    /// #[derive(Clone)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Neg(Box<Expr>),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Num(_) => [None, None],
+    /// #             Neg(e) => [Some(&**e), None],
+    /// #             Add(a, b) => [Some(&**a), Some(&**b)],
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// impl TreeWalk<Self> for Expr {
+    ///     fn each_branch(self, mut f: impl FnMut(Self) -> Self) -> Self {
+    ///         match self {
+    ///             Num(_) => self,
+    ///             Neg(e) => Neg(Box::new(f(*e))),
+    ///             Add(a, b) => Add(Box::new(f(*a)), Box::new(f(*b))),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn kind(expr: &Expr) -> &'static str {
+    ///     match expr {
+    ///         Num(_) => "num",
+    ///         Neg(_) => "neg",
+    ///         Add(..) => "add",
+    ///     }
+    /// }
+    ///
+    /// let tree = Add(Box::new(Num(1)), Box::new(Neg(Box::new(Num(2)))));
+    /// let mut parents = Vec::new();
+    /// tree.bottom_up_with_parent(|parent, node| {
+    ///     parents.push((kind(&node), parent.map(kind)));
+    ///     node
+    /// });
+    ///
+    /// assert_eq!(
+    ///     parents,
+    ///     [
+    ///         ("num", Some("add")),
+    ///         ("num", Some("neg")),
+    ///         ("neg", Some("add")),
+    ///         ("add", None),
+    ///     ]
+    /// );
+    /// ```
+    fn bottom_up_with_parent(
+        self,
+        mut f: impl FnMut(Option<&Self>, Self) -> FS,
+    ) -> FS
+    where
+        Self: Clone + Children,
+    {
+        fn go<S: TreeWalk<FS> + Children, FS>(
+            branch: S,
+            original: &S,
+            parent: Option<&S>,
+            f: &mut impl FnMut(Option<&S>, S) -> FS,
+        ) -> FS {
+            let mut originals = original.children();
+            let rest_transformed = branch.each_branch(|branch| {
+                let child = originals
+                    .next()
+                    .expect("`each_branch` and `children` disagree");
+                go(branch, child, Some(original), f)
+            });
+            Bind::bind_mut(rest_transformed, |branch| f(parent, branch))
+        }
+        let original = self.clone();
+        go(self, &original, None, &mut f)
+    }
+
+    /// A version of [`bottom_up`] that passes a shared context to every
+    /// invocation of the function.
+    ///
+    /// This is equivalent to capturing `ctx` in the closure, but avoids having
+    /// to spell out the lifetimes involved when the closure is built by a
+    /// recursive helper function.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use trexp::TreeWalk;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Var(&'static str),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    ///
+    /// impl TreeWalk<Self> for Expr {
+    ///     fn each_branch(self, mut f: impl FnMut(Self) -> Self) -> Self {
+    ///         match self {
+    ///             Add(a, b) => Add(Box::new(f(*a)), Box::new(f(*b))),
+    ///             _ => self,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn substitute(vars: &HashMap<&str, i32>, expr: Expr) -> Expr {
+    ///     match expr {
+    ///         Var(name) => vars.get(name).map_or(expr, |&value| Num(value)),
+    ///         _ => expr,
+    ///     }
+    /// }
+    ///
+    /// let vars = HashMap::from([("x", 1), ("y", 2)]);
+    /// let tree = Add(Box::new(Var("x")), Box::new(Var("z")));
+    ///
+    /// assert_eq!(
+    ///     Add(Box::new(Num(1)), Box::new(Var("z"))),
+    ///     tree.bottom_up_ctx(&vars, substitute),
+    /// );
+    /// ```
+    fn bottom_up_ctx<C: ?Sized>(
+        self,
+        ctx: &C,
+        mut f: impl FnMut(&C, Self) -> FS,
+    ) -> FS {
+        self.bottom_up(|branch| f(ctx, branch))
+    }
+
+    /// A version of [`bottom_up`] that also passes the path to each node to the
+    /// function.
+    ///
+    /// A path is the sequence of branch indices leading from the root to the
+    /// node, where the index of a branch is its position in the order that
+    /// [`each_branch`] visits it. The root has the empty path.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`each_branch`]: TreeWalk::each_branch
+    #[cfg(feature = "alloc")]
+    fn bottom_up_with_path(
+        self,
+        mut f: impl FnMut(&[usize], Self) -> FS,
+    ) -> FS {
+        fn go<S: TreeWalk<FS>, FS>(
+            branch: S,
+            path: &mut Vec<usize>,
+            f: &mut impl FnMut(&[usize], S) -> FS,
+        ) -> FS {
+            let mut index = 0;
+            let rest_transformed = branch.each_branch(|branch| {
+                path.push(index);
+                index += 1;
+                let transformed = go(branch, path, f);
+                path.pop();
+                transformed
+            });
+            Bind::bind_mut(rest_transformed, |branch| f(path, branch))
+        }
+        go(self, &mut Vec::new(), &mut f)
+    }
+
+    /// A version of [`bottom_up`] that also passes the index of each node among
+    /// its siblings to the function.
+    ///
+    /// The index of a branch is its position in the order that
+    /// [`each_branch`] visits it, which is the same as the last element of its
+    /// path in [`bottom_up_with_path`]. The root has no siblings and gets the
+    /// index 0.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`each_branch`]: TreeWalk::each_branch
+    /// [`bottom_up_with_path`]: TreeWalk::bottom_up_with_path
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::TreeWalk;
+    /// enum Expr {
+    ///     Num(i32),
+    ///     If(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    ///
+    /// impl TreeWalk<Self> for Expr {
+    ///     fn each_branch(self, mut f: impl FnMut(Self) -> Self) -> Self {
+    ///         match self {
+    ///             Num(_) => self,
+    ///             If(c, t, e) => {
+    ///                 If(Box::new(f(*c)), Box::new(f(*t)), Box::new(f(*e)))
+    ///             }
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let tree = If(Box::new(Num(0)), Box::new(Num(1)), Box::new(Num(2)));
+    /// let mut seen = Vec::new();
+    /// tree.bottom_up_indexed(|index, expr| {
+    ///     if let Num(n) = expr {
+    ///         seen.push((index, n));
+    ///     } else {
+    ///         seen.push((index, -1));
+    ///     }
+    ///     expr
+    /// });
+    ///
+    /// assert_eq!(vec![(0, 0), (1, 1), (2, 2), (0, -1)], seen);
+    /// ```
+    fn bottom_up_indexed(self, mut f: impl FnMut(usize, Self) -> FS) -> FS {
+        fn go<S: TreeWalk<FS>, FS>(
+            branch: S,
+            index: usize,
+            f: &mut impl FnMut(usize, S) -> FS,
+        ) -> FS {
+            let mut child_index = 0;
+            let rest_transformed = branch.each_branch(|branch| {
+                let transformed = go(branch, child_index, f);
+                child_index += 1;
+                transformed
+            });
+            Bind::bind_mut(rest_transformed, |branch| f(index, branch))
+        }
+        go(self, 0, &mut f)
+    }
+
+    /// Applies an effectful function to every node of the tree that has no
+    /// branches, leaving the other nodes as they are.
+    ///
+    /// A node counts as a leaf if [`each_branch`] doesn't call its function.
+    ///
+    /// [`each_branch`]: TreeWalk::each_branch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::TreeWalk;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Neg(Box<Expr>),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Self> for Expr {
+    /// #     fn each_branch(self, mut f: impl FnMut(Self) -> Self) -> Self {
+    /// #         match self {
+    /// #             Num(_) => self,
+    /// #             Neg(e) => Neg(Box::new(f(*e))),
+    /// #             Add(a, b) => Add(Box::new(f(*a)), Box::new(f(*b))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// // Would replace any node other than a number with zero
+    /// let increment = |expr| match expr {
+    ///     Num(n) => Num(n + 1),
+    ///     _ => Num(0),
+    /// };
+    ///
+    /// let tree = add(Num(1), Neg(Box::new(Num(2))));
+    ///
+    /// assert_eq!(
+    ///     add(Num(2), Neg(Box::new(Num(3)))),
+    ///     tree.rewrite_leaves(increment),
+    /// );
+    /// ```
+    fn rewrite_leaves(self, mut f: impl FnMut(Self) -> FS) -> FS {
+        fn go<S: TreeWalk<FS>, FS>(
+            branch: S,
+            f: &mut impl FnMut(S) -> FS,
+        ) -> FS {
+            let mut has_branches = false;
+            let rest_transformed = branch.each_branch(|branch| {
+                has_branches = true;
+                go(branch, f)
+            });
+            if has_branches {
+                rest_transformed
+            } else {
+                Bind::bind_mut(rest_transformed, f)
+            }
+        }
+        go(self, &mut f)
+    }
+
+    /// A version of [`rewrite_leaves`] that also passes the position of each
+    /// leaf among all leaves of the tree to the function.
+    ///
+    /// Leaves are numbered from 0 in the order that [`each_branch`] visits
+    /// them, which is usually left to right.
+    ///
+    /// [`rewrite_leaves`]: TreeWalk::rewrite_leaves
+    /// [`each_branch`]: TreeWalk::each_branch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::TreeWalk;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Hole,
+    ///     Slot(usize),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    ///
+    /// impl TreeWalk<Self> for Expr {
+    ///     fn each_branch(self, mut f: impl FnMut(Self) -> Self) -> Self {
+    ///         match self {
+    ///             Add(a, b) => Add(Box::new(f(*a)), Box::new(f(*b))),
+    ///             _ => self,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let tree = add(add(Hole, Hole), add(Hole, add(Hole, Hole)));
+    ///
+    /// assert_eq!(
+    ///     add(add(Slot(0), Slot(1)), add(Slot(2), add(Slot(3), Slot(4)))),
+    ///     tree.rewrite_leaves_enumerated(|index, _| Slot(index)),
+    /// );
+    /// ```
+    fn rewrite_leaves_enumerated(
+        self,
+        mut f: impl FnMut(usize, Self) -> FS,
+    ) -> FS {
+        let mut index = 0;
+        self.rewrite_leaves(|leaf| {
+            let transformed = f(index, leaf);
+            index += 1;
+            transformed
+        })
+    }
+
+    /// A version of [`bottom_up`] that walks another tree of the same shape in
+    /// lockstep, passing the corresponding node of `other` to the function
+    /// along with each node of `self`.
+    ///
+    /// Nodes are paired up by their position among their siblings. Returns
+    /// `None` without calling the function at all if the two trees have
+    /// different shapes, meaning that some pair of corresponding nodes have
+    /// different numbers of branches.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Children, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Self> for Expr {
+    /// #     fn each_branch(self, mut f: impl FnMut(Self) -> Self) -> Self {
+    /// #         match self {
+    /// #             Num(_) => self,
+    /// #             Add(a, b) => Add(Box::new(f(*a)), Box::new(f(*b))),
+    /// #         }
+    /// #     }
+    /// # }
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Num(_) => None,
+    /// #             Add(a, b) => Some([&**a, &**b]),
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let add_numbers = |expr, other: &Expr| match (expr, other) {
+    ///     (Num(a), Num(b)) => Num(a + b),
+    ///     (expr, _) => expr,
+    /// };
+    ///
+    /// assert_eq!(
+    ///     Some(add(Num(11), Num(22))),
+    ///     add(Num(1), Num(2)).zip_bottom_up(&add(Num(10), Num(20)), add_numbers),
+    /// );
+    /// assert_eq!(
+    ///     None,
+    ///     add(Num(1), Num(2)).zip_bottom_up(&Num(10), add_numbers),
+    /// );
+    /// ```
+    fn zip_bottom_up(
+        self,
+        other: &Self,
+        mut f: impl FnMut(Self, &Self) -> FS,
+    ) -> Option<FS>
+    where
+        Self: Children,
+    {
+        fn same_shape<S: Children>(a: &S, b: &S) -> bool {
+            let (mut a, mut b) = (a.children(), b.children());
+            loop {
+                match (a.next(), b.next()) {
+                    (Some(a), Some(b)) if same_shape(a, b) => {}
+                    (None, None) => break true,
+                    _ => break false,
+                }
+            }
+        }
+
+        fn go<S: TreeWalk<FS> + Children, FS>(
+            branch: S,
+            other: &S,
+            f: &mut impl FnMut(S, &S) -> FS,
+        ) -> FS {
+            let mut others = other.children();
+            let rest_transformed = branch.each_branch(|branch| {
+                let other = others
+                    .next()
+                    .expect("`each_branch` and `children` disagree");
+                go(branch, other, f)
+            });
+            Bind::bind_mut(rest_transformed, |branch| f(branch, other))
+        }
+
+        same_shape(&self, other).then(|| go(self, other, &mut f))
+    }
+
+    /// A version of [`bottom_up`] that transforms sibling subtrees in
+    /// parallel using [`rayon::join`].
+    ///
+    /// Since [`each_branch`] visits branches one at a time, each node is taken
+    /// apart by using the identity [`each_branch`] to swap its branches out
+    /// for [`Default`] placeholders, and put back together by calling
+    /// [`each_branch`] again on the result. Implementors must therefore visit
+    /// the same number of branches, in the same order, on the node with
+    /// placeholders as on the original, and should make [`Default`] cheap.
+    ///
+    /// Taking a node apart costs an allocation, and handing its branches to
+    /// rayon costs a few atomic operations per branch, which is more than
+    /// applying a typical rewrite rule. The branches of nodes with fewer than
+    /// `min_branches` branches are therefore transformed one after another on
+    /// the current thread, although wider nodes further down are still split
+    /// up. A `min_branches` of 2 splits up every node with more than one
+    /// branch, while larger values restrict parallelism to wide nodes so that
+    /// small subtrees such as unary or binary operators don't pay for it. For
+    /// pure functions the result is the same as that of [`bottom_up`].
+    ///
+    /// Only available with the `rayon` feature.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`each_branch`]: TreeWalk::each_branch
+    /// [`Default`]: core::default::Default
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`each_branch`] visits more branches when putting a node back
+    /// together than it did when taking it apart.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::TreeWalk;
+    /// #[derive(Debug, Default, PartialEq)]
+    /// enum Tree {
+    ///     #[default]
+    ///     Empty,
+    ///     Leaf(u64),
+    ///     Node(Vec<Tree>),
+    /// }
+    /// # use Tree::*;
+    ///
+    /// impl TreeWalk<Self> for Tree {
+    ///     fn each_branch(self, f: impl FnMut(Self) -> Self) -> Self {
+    ///         match self {
+    ///             Node(branches) => Node(branches.into_iter().map(f).collect()),
+    ///             _ => self,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn sum_leaves(tree: Tree) -> Tree {
+    ///     match tree {
+    ///         Node(branches) => Leaf(
+    ///             branches
+    ///                 .into_iter()
+    ///                 .map(|branch| match branch {
+    ///                     Leaf(n) => n,
+    ///                     _ => 0,
+    ///                 })
+    ///                 .sum(),
+    ///         ),
+    ///         Leaf(n) => Leaf(n * n),
+    ///         Empty => Empty,
+    ///     }
+    /// }
+    ///
+    /// let wide_tree = || {
+    ///     Node((0..64).map(|i| Node((0..i).map(Leaf).collect())).collect())
+    /// };
+    ///
+    /// for min_branches in [2, 16, 1000] {
+    ///     assert_eq!(
+    ///         wide_tree().bottom_up(sum_leaves),
+    ///         wide_tree().par_bottom_up(min_branches, sum_leaves),
+    ///     );
+    /// }
+    /// ```
+    #[cfg(feature = "rayon")]
+    fn par_bottom_up(
+        self,
+        min_branches: usize,
+        f: impl Fn(Self) -> FS + Sync,
+    ) -> FS
+    where
+        Self: TreeWalk<Self> + Default + Send,
+        FS: Send,
+    {
+        fn go<S, FS>(
+            branch: S,
+            min_branches: usize,
+            f: &(impl Fn(S) -> FS + Sync),
+        ) -> FS
+        where
+            S: TreeWalk<FS> + TreeWalk<S> + Default + Send,
+            FS: Send,
+        {
+            let mut branches = Vec::new();
+            let shell = TreeWalk::<S>::each_branch(branch, |branch| {
+                branches.push(branch);
+                S::default()
+            });
+
+            let mut transformed = if branches.len() < min_branches {
+                branches
+                    .into_iter()
+                    .map(|branch| go(branch, min_branches, f))
+                    .collect()
+            } else {
+                par_map(branches, min_branches, f)
+            }
+            .into_iter();
+            let rest_transformed = TreeWalk::<FS>::each_branch(shell, |_| {
+                transformed
+                    .next()
+                    .expect("`each_branch` visited more branches")
+            });
+            Bind::bind_mut(rest_transformed, f)
+        }
+
+        fn par_map<S, FS>(
+            mut branches: Vec<S>,
+            min_branches: usize,
+            f: &(impl Fn(S) -> FS + Sync),
+        ) -> Vec<FS>
+        where
+            S: TreeWalk<FS> + TreeWalk<S> + Default + Send,
+            FS: Send,
+        {
+            if branches.len() < 2 {
+                return branches
+                    .into_iter()
+                    .map(|branch| go(branch, min_branches, f))
+                    .collect();
+            }
+            let right = branches.split_off(branches.len() / 2);
+            let (mut left, right) = rayon::join(
+                || par_map(branches, min_branches, f),
+                || par_map(right, min_branches, f),
+            );
+            left.extend(right);
+            left
+        }
+
+        go(self, min_branches, &f)
+    }
+}
+
+/// Trait for tree-like structures whose edges carry labels, such as field
+/// names.
+pub trait LabeledTreeWalk<L, FS>: Bind<FS> {
+    /// Applies an effectful function to each branch of the tree along with the
+    /// label of the edge leading to it, wrapping the final value in the same
+    /// type of effect.
+    fn each_labeled_branch(self, f: impl FnMut(&L, Self) -> FS) -> FS;
+
+    /// A version of [`TreeWalk::bottom_up`] that also passes the label of the
+    /// edge leading to each node to the function, or `None` for the root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::LabeledTreeWalk;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Value {
+    ///     Num(i32),
+    ///     Record(Vec<(&'static str, Value)>),
+    /// }
+    /// # use Value::*;
+    ///
+    /// impl LabeledTreeWalk<&'static str, Self> for Value {
+    ///     fn each_labeled_branch(
+    ///         self,
+    ///         mut f: impl FnMut(&&'static str, Self) -> Self,
+    ///     ) -> Self {
+    ///         match self {
+    ///             Num(_) => self,
+    ///             Record(fields) => Record(
+    ///                 fields
+    ///                     .into_iter()
+    ///                     .map(|(name, value)| (name, f(&name, value)))
+    ///                     .collect(),
+    ///             ),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let point = Record(vec![
+    ///     ("x", Num(1)),
+    ///     ("y", Num(2)),
+    ///     ("meta", Record(vec![("id", Num(3))])),
+    /// ]);
+    /// let mut labels = Vec::new();
+    /// point.bottom_up_with_edge(|label, value| {
+    ///     labels.push(label.copied());
+    ///     value
+    /// });
+    ///
+    /// assert_eq!(
+    ///     vec![Some("x"), Some("y"), Some("id"), Some("meta"), None],
+    ///     labels,
+    /// );
+    /// ```
+    fn bottom_up_with_edge(
+        self,
+        mut f: impl FnMut(Option<&L>, Self) -> FS,
+    ) -> FS {
+        fn go<S: LabeledTreeWalk<L, FS>, L, FS>(
+            branch: S,
+            label: Option<&L>,
+            f: &mut impl FnMut(Option<&L>, S) -> FS,
+        ) -> FS {
+            let rest_transformed =
+                branch.each_labeled_branch(|label, branch| {
+                    go(branch, Some(label), f)
+                });
+            Bind::bind_mut(rest_transformed, |branch| f(label, branch))
+        }
+        go(self, None, &mut f)
+    }
+}
+
+/// Trait for one of two mutually recursive tree-like structures, such as
+/// expressions and statements that can contain each other, that can be
+/// recursively transformed with effectful computations.
+///
+/// `Other` is the other type, and `FS` and `FO` are `Self` and `Other` wrapped
+/// in the effect, respectively. Both types should implement this trait, each
+/// with the other one as `Other` and the effects swapped around.
+pub trait TreeWalk2<Other, FS, FO>: Bind<FS> {
+    /// Applies one effectful function to each branch of type `Self` and
+    /// another to each branch of type `Other`, wrapping the final value in the
+    /// same type of effect.
+    fn each_branch2(
+        self,
+        f: impl FnMut(Self) -> FS,
+        g: impl FnMut(Other) -> FO,
+    ) -> FS;
+
+    /// Applies one effectful function to every node of type `Self` and another
+    /// to every node of type `Other`, including the root itself, in a
+    /// bottom-up manner.
+    ///
+    /// This crosses back and forth between the two types as needed, so every
+    /// node is transformed after all of its branches regardless of their type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::cell::RefCell;
+    /// # use trexp::TreeWalk2;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    ///     Block(Vec<Stmt>, Box<Expr>),
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Stmt {
+    ///     Print(Expr),
+    ///     Nop,
+    /// }
+    /// # use {Expr::*, Stmt::*};
+    ///
+    /// impl TreeWalk2<Stmt, Self, Stmt> for Expr {
+    ///     fn each_branch2(
+    ///         self,
+    ///         mut f: impl FnMut(Self) -> Self,
+    ///         g: impl FnMut(Stmt) -> Stmt,
+    ///     ) -> Self {
+    ///         match self {
+    ///             Num(_) => self,
+    ///             Add(a, b) => Add(Box::new(f(*a)), Box::new(f(*b))),
+    ///             Block(stmts, e) => Block(
+    ///                 stmts.into_iter().map(g).collect(),
+    ///                 Box::new(f(*e)),
+    ///             ),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// impl TreeWalk2<Expr, Self, Expr> for Stmt {
+    ///     fn each_branch2(
+    ///         self,
+    ///         _: impl FnMut(Self) -> Self,
+    ///         mut g: impl FnMut(Expr) -> Expr,
+    ///     ) -> Self {
+    ///         match self {
+    ///             Print(e) => Print(g(e)),
+    ///             Nop => Nop,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let tree = Block(
+    ///     vec![Print(add(Num(1), Num(2))), Nop],
+    ///     Box::new(add(Num(3), Num(4))),
+    /// );
+    /// let visited = RefCell::new(Vec::new());
+    /// let folded = tree.bottom_up2(
+    ///     |expr| {
+    ///         visited.borrow_mut().push("expr");
+    ///         match expr {
+    ///             Add(a, b) => match (*a, *b) {
+    ///                 (Num(a), Num(b)) => Num(a + b),
+    ///                 (a, b) => add(a, b),
+    ///             },
+    ///             Block(stmts, e) if stmts.is_empty() => *e,
+    ///             _ => expr,
+    ///         }
+    ///     },
+    ///     |stmt| {
+    ///         visited.borrow_mut().push("stmt");
+    ///         stmt
+    ///     },
+    /// );
+    ///
+    /// assert_eq!(Block(vec![Print(Num(3)), Nop], Box::new(Num(7))), folded);
+    /// assert_eq!(
+    ///     vec!["expr", "expr", "expr", "stmt", "stmt", "expr", "expr", "expr", "expr"],
+    ///     visited.into_inner(),
+    /// );
+    /// ```
+    fn bottom_up2(
+        self,
+        f: impl FnMut(Self) -> FS,
+        g: impl FnMut(Other) -> FO,
+    ) -> FS
+    where
+        Other: TreeWalk2<Self, FO, FS>,
+    {
+        // Both closures passed to `each_branch2` need access to both
+        // functions, so they are shared through `RefCell`s. Each function is
+        // only borrowed while it's being called, which never overlaps.
+        fn go<S: TreeWalk2<O, FS, FO>, O: TreeWalk2<S, FO, FS>, FS, FO>(
+            branch: S,
+            f: &RefCell<impl FnMut(S) -> FS>,
+            g: &RefCell<impl FnMut(O) -> FO>,
+        ) -> FS {
+            let rest_transformed = branch.each_branch2(
+                |branch| go(branch, f, g),
+                |other| go(other, g, f),
+            );
+            Bind::bind_mut(rest_transformed, |branch| (f.borrow_mut())(branch))
+        }
+        go(self, &RefCell::new(f), &RefCell::new(g))
+    }
+}
+
+/// Trait for tree-like structures with a variable number of branches stored in
+/// a [`Vec`], such as blocks of statements or argument lists.
+///
+/// Only available with the `alloc` feature.
+///
+/// [`Vec`]: alloc::vec::Vec
+#[cfg(feature = "alloc")]
+pub trait ChildVec: Sized {
+    /// Returns the list of branches of the node, or `None` if the node doesn't
+    /// have one.
+    fn child_vec(&mut self) -> Option<&mut Vec<Self>>;
+
+    /// Returns bounds on the number of branches that the node is expected to
+    /// have after being transformed, in the same form as
+    /// [`Iterator::size_hint`].
+    ///
+    /// This is only used to reserve space up front, so it doesn't have to be
+    /// accurate. The default implementation returns `(0, None)`, which is
+    /// correct for any node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::ChildVec;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Stmt {
+    ///     Print(&'static str),
+    ///     Block(Vec<Stmt>),
+    /// }
+    /// # use Stmt::*;
+    ///
+    /// impl ChildVec for Stmt {
+    ///     fn child_vec(&mut self) -> Option<&mut Vec<Self>> {
+    ///         match self {
+    ///             Block(stmts) => Some(stmts),
+    ///             Print(_) => None,
+    ///         }
+    ///     }
+    ///
+    ///     fn child_count_hint(&self) -> (usize, Option<usize>) {
+    ///         match self {
+    ///             Block(stmts) => (stmts.len() * 2, Some(stmts.len() * 2)),
+    ///             Print(_) => (0, Some(0)),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let block = Block(vec![Print("a"), Print("b"), Print("c")]);
+    /// let mut doubled = block.flat_map_children(|stmt| vec![stmt, Print(",")]);
+    ///
+    /// // Without the hint, the vector would have grown to a capacity of 8
+    /// assert_eq!(6, doubled.child_vec().unwrap().capacity());
+    /// ```
+    fn child_count_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+
+    /// Replaces each direct branch of the node with any number of new
+    /// branches.
+    ///
+    /// Nodes without a list of branches are returned unchanged. Space for the
+    /// new branches is reserved according to [`child_count_hint`].
+    ///
+    /// [`child_count_hint`]: ChildVec::child_count_hint
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::ChildVec;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Stmt {
+    ///     Print(&'static str),
+    ///     PrintTwice(&'static str),
+    ///     Block(Vec<Stmt>),
+    /// }
+    /// # use Stmt::*;
+    ///
+    /// impl ChildVec for Stmt {
+    ///     fn child_vec(&mut self) -> Option<&mut Vec<Self>> {
+    ///         match self {
+    ///             Block(stmts) => Some(stmts),
+    ///             _ => None,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let block = Block(vec![Print("a"), PrintTwice("b")]);
+    /// let expanded = block.flat_map_children(|stmt| match stmt {
+    ///     PrintTwice(s) => vec![Print(s), Print(s)],
+    ///     _ => vec![stmt],
+    /// });
+    ///
+    /// assert_eq!(Block(vec![Print("a"), Print("b"), Print("b")]), expanded);
+    /// ```
+    #[must_use]
+    fn flat_map_children(mut self, f: impl FnMut(Self) -> Vec<Self>) -> Self {
+        let (expected, _) = self.child_count_hint();
+        if let Some(children) = self.child_vec() {
+            let mut flattened = Vec::with_capacity(expected);
+            flattened.extend(core::mem::take(children).into_iter().flat_map(f));
+            *children = flattened;
+        }
+        self
+    }
 }
+
+/// Trait for tree-like structures whose branches can be inspected without
+/// consuming the tree.
+pub trait Children: Sized {
+    /// Returns an iterator over the direct branches of the tree.
+    ///
+    /// Types that also implement [`TreeWalk`] should yield branches in the same
+    /// order that [`each_branch`] visits them, so that paths agree between the
+    /// two traits.
+    ///
+    /// [`TreeWalk`]: TreeWalk
+    /// [`each_branch`]: TreeWalk::each_branch
+    fn children(&self) -> impl Iterator<Item = &Self>;
+
+    /// Returns `true` if the tree has no branches.
+    fn is_leaf(&self) -> bool {
+        self.children().next().is_none()
+    }
+
+    /// Returns the number of direct branches of the tree.
+    fn arity(&self) -> usize {
+        self.children().count()
+    }
+
+    /// Walks down the given path from the root, returning the deepest node
+    /// along the way that satisfies a predicate.
+    ///
+    /// Paths are numbered like in [`TreeWalk::bottom_up_with_path`]. The root
+    /// and the node at the end of the path are both candidates. If the path
+    /// leads outside of the tree, the walk stops at the deepest node that
+    /// exists along it. This is useful for things like finding the innermost
+    /// scope that encloses a position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Var(char),
+    ///     Let(char, Box<Expr>),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Var(_) => [None, None],
+    /// #             Let(_, e) => [Some(&**e), None],
+    /// #             Add(a, b) => [Some(&**a), Some(&**b)],
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// // let x in (x + let y in y)
+    /// let tree = Let(
+    ///     'x',
+    ///     Box::new(Add(
+    ///         Box::new(Var('x')),
+    ///         Box::new(Let('y', Box::new(Var('y')))),
+    ///     )),
+    /// );
+    /// let is_let = |expr: &Expr| matches!(expr, Let(..));
+    /// let binder = |path| match tree.deepest_on_path(path, is_let) {
+    ///     Some(Let(name, _)) => Some(*name),
+    ///     _ => None,
+    /// };
+    ///
+    /// assert_eq!(Some('x'), binder(&[0, 0]));
+    /// assert_eq!(Some('y'), binder(&[0, 1, 0]));
+    /// assert_eq!(Some('y'), binder(&[0, 1]));
+    /// assert_eq!(None, Var('z').deepest_on_path(&[], is_let));
+    /// ```
+    fn deepest_on_path<'a>(
+        &'a self,
+        path: &[usize],
+        mut pred: impl FnMut(&Self) -> bool,
+    ) -> Option<&'a Self> {
+        let mut node = self;
+        let mut deepest = pred(node).then_some(node);
+        for &index in path {
+            let Some(child) = node.children().nth(index) else {
+                break;
+            };
+            node = child;
+            if pred(node) {
+                deepest = Some(node);
+            }
+        }
+        deepest
+    }
+
+    /// Sums up a value computed for every node of the tree, including the root
+    /// itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// struct Tree(u32, Vec<Tree>);
+    ///
+    /// impl Children for Tree {
+    ///     fn children(&self) -> impl Iterator<Item = &Self> {
+    ///         self.1.iter()
+    ///     }
+    /// }
+    ///
+    /// let tree = Tree(1, vec![Tree(5, vec![]), Tree(2, vec![Tree(3, vec![])])]);
+    ///
+    /// assert_eq!(11, tree.sum_by(|node| node.0));
+    /// assert_eq!(4, tree.sum_by(|_| 1));
+    /// ```
+    fn sum_by<N: core::iter::Sum>(&self, mut f: impl FnMut(&Self) -> N) -> N {
+        fn go<S: Children, N: core::iter::Sum>(
+            node: &S,
+            f: &mut impl FnMut(&S) -> N,
+        ) -> N {
+            core::iter::once(f(node))
+                .chain(node.children().map(|child| go(child, f)))
+                .sum()
+        }
+        go(self, &mut f)
+    }
+
+    /// Returns the node of the tree, including the root itself, for which a
+    /// function returns the largest key.
+    ///
+    /// Like [`Iterator::max_by_key`], the last node in pre-order is returned if
+    /// several nodes are equally maximal. This never returns `None`, since
+    /// every tree has at least one node, but the `Option` keeps it in line
+    /// with its iterator counterpart.
+    ///
+    /// [`Iterator::max_by_key`]: core::iter::Iterator::max_by_key
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// struct Tree(u32, Vec<Tree>);
+    ///
+    /// impl Children for Tree {
+    ///     fn children(&self) -> impl Iterator<Item = &Self> {
+    ///         self.1.iter()
+    ///     }
+    /// }
+    ///
+    /// let tree = Tree(1, vec![Tree(5, vec![]), Tree(2, vec![Tree(3, vec![])])]);
+    ///
+    /// assert_eq!(Some(5), tree.max_by_key(|node| node.0).map(|node| node.0));
+    /// assert_eq!(Some(3), tree.max_by_key(|_| 0).map(|node| node.0));
+    /// ```
+    fn max_by_key<K: Ord>(
+        &self,
+        mut f: impl FnMut(&Self) -> K,
+    ) -> Option<&Self> {
+        fn go<'a, S: Children, K: Ord>(
+            node: &'a S,
+            f: &mut impl FnMut(&S) -> K,
+            best: &mut Option<(K, &'a S)>,
+        ) {
+            let key = f(node);
+            if best.as_ref().is_none_or(|(best_key, _)| key >= *best_key) {
+                *best = Some((key, node));
+            }
+            for child in node.children() {
+                go(child, f, best);
+            }
+        }
+        let mut best = None;
+        go(self, &mut f, &mut best);
+        best.map(|(_, node)| node)
+    }
+
+    /// Returns the largest number of direct branches of any node in the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// struct Tree(Vec<Tree>);
+    ///
+    /// impl Children for Tree {
+    ///     fn children(&self) -> impl Iterator<Item = &Self> {
+    ///         self.0.iter()
+    ///     }
+    /// }
+    ///
+    /// let leaf = || Tree(vec![]);
+    /// let tree = Tree(vec![leaf(), Tree(vec![leaf(), leaf(), leaf()])]);
+    ///
+    /// assert!(!tree.is_leaf());
+    /// assert!(leaf().is_leaf());
+    /// assert_eq!(2, tree.arity());
+    /// assert_eq!(0, leaf().arity());
+    /// assert_eq!(3, tree.max_arity());
+    /// assert_eq!(0, leaf().max_arity());
+    /// ```
+    fn max_arity(&self) -> usize {
+        self.children()
+            .map(Self::max_arity)
+            .fold(self.arity(), usize::max)
+    }
+
+    /// Returns an iterator over every node of the tree, including the root
+    /// itself, in pre-order.
+    ///
+    /// Only available with the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    fn pre_order(&self) -> impl Iterator<Item = &Self> {
+        let mut stack = Vec::from([self.children()]);
+        let descendants = core::iter::from_fn(move || loop {
+            match stack.last_mut()?.next() {
+                Some(child) => {
+                    stack.push(child.children());
+                    break Some(child);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        });
+        core::iter::once(self).chain(descendants)
+    }
+
+    /// Returns an iterator over every node of the tree that has no branches,
+    /// in pre-order.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Var(char),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Add(a, b) => Some([&**a, &**b]),
+    /// #             _ => None,
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// let tree = Add(
+    ///     Box::new(Add(Box::new(Num(1)), Box::new(Var('x')))),
+    ///     Box::new(Num(2)),
+    /// );
+    ///
+    /// assert_eq!(
+    ///     vec![&Num(1), &Var('x'), &Num(2)],
+    ///     tree.leaves().collect::<Vec<_>>(),
+    /// );
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn leaves(&self) -> impl Iterator<Item = &Self> {
+        self.pre_order().filter(|node| node.is_leaf())
+    }
+
+    /// Returns an iterator over every node of the tree, including the root
+    /// itself, that satisfies a predicate, in pre-order.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Variable(char),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Add(a, b) => Some([&**a, &**b]),
+    /// #             _ => None,
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// let tree = Add(
+    ///     Box::new(Variable('x')),
+    ///     Box::new(Add(Box::new(Num(1)), Box::new(Variable('y')))),
+    /// );
+    ///
+    /// assert_eq!(
+    ///     vec![&Variable('x'), &Variable('y')],
+    ///     tree.descendants(|expr| matches!(expr, Variable(_)))
+    ///         .collect::<Vec<_>>(),
+    /// );
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn descendants<'a>(
+        &'a self,
+        mut pred: impl FnMut(&Self) -> bool + 'a,
+    ) -> impl Iterator<Item = &'a Self> {
+        self.pre_order().filter(move |node| pred(node))
+    }
+
+    /// Collects every node of the tree, including the root itself, in
+    /// post-order, paired with its index in that order.
+    ///
+    /// Post-order visits children before their parents, which is the order
+    /// that a stack machine evaluates an expression in. The root is always
+    /// last.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Neg(Box<Expr>),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Num(_) => [None, None],
+    /// #             Neg(e) => [Some(&**e), None],
+    /// #             Add(a, b) => [Some(&**a), Some(&**b)],
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// let tree = Add(Box::new(Neg(Box::new(Num(1)))), Box::new(Num(2)));
+    /// let numbering = tree.postorder_numbering();
+    ///
+    /// assert_eq!(
+    ///     vec![
+    ///         (0, &Num(1)),
+    ///         (1, &Neg(Box::new(Num(1)))),
+    ///         (2, &Num(2)),
+    ///         (3, &tree),
+    ///     ],
+    ///     numbering,
+    /// );
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn postorder_numbering(&self) -> Vec<(usize, &Self)> {
+        fn go<'a, S: Children>(
+            node: &'a S,
+            numbering: &mut Vec<(usize, &'a S)>,
+        ) {
+            for child in node.children() {
+                go(child, numbering);
+            }
+            numbering.push((numbering.len(), node));
+        }
+        let mut numbering = Vec::new();
+        go(self, &mut numbering);
+        numbering
+    }
+
+    /// Finds every subtree that occurs more than once in the tree, along with
+    /// the number of times that it occurs.
+    ///
+    /// The root itself is included, and subtrees of duplicates are counted
+    /// once per occurrence. This is useful for common subexpression
+    /// elimination.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use trexp::Children;
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Var(char),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Add(a, b) => Some([&**a, &**b]),
+    /// #             _ => None,
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// // (x + 1) + ((x + 1) + 2)
+    /// let x_plus_1 = || add(Var('x'), Num(1));
+    /// let tree = add(x_plus_1(), add(x_plus_1(), Num(2)));
+    ///
+    /// assert_eq!(
+    ///     HashMap::from([(x_plus_1(), 2), (Var('x'), 2), (Num(1), 2)]),
+    ///     tree.duplicate_subtrees(),
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    fn duplicate_subtrees(&self) -> HashMap<Self, usize>
+    where
+        Self: Hash + Eq + Clone,
+    {
+        let mut counts = HashMap::<&Self, usize>::new();
+        for node in self.pre_order() {
+            *counts.entry(node).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .filter(|&(_, count)| count > 1)
+            .map(|(node, count)| (node.clone(), count))
+            .collect()
+    }
+
+    /// Walks the tree top-down, threading an accumulator from the root to each
+    /// node.
+    ///
+    /// `visit` is called with every node, including the root, along with its
+    /// accumulator. The accumulator of the root is `root_acc`, and the
+    /// accumulator shared by the branches of a node is computed by calling
+    /// `descend` with the accumulator of the node and the node itself. This is
+    /// the shape of an inherited attribute, such as the set of variables that
+    /// are in scope.
+    ///
+    /// Nodes are visited in pre-order, and `descend` is only called for nodes
+    /// that have branches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// struct Tree(char, Vec<Tree>);
+    ///
+    /// impl Children for Tree {
+    ///     fn children(&self) -> impl Iterator<Item = &Self> {
+    ///         self.1.iter()
+    ///     }
+    /// }
+    ///
+    /// let leaf = |name| Tree(name, vec![]);
+    /// let tree = Tree('a', vec![leaf('b'), Tree('c', vec![leaf('d')])]);
+    ///
+    /// let mut depths = Vec::new();
+    /// tree.scan_top_down(
+    ///     0,
+    ///     |depth, _| depth + 1,
+    ///     |depth, node| depths.push((node.0, *depth)),
+    /// );
+    ///
+    /// assert_eq!(vec![('a', 0), ('b', 1), ('c', 1), ('d', 2)], depths);
+    /// ```
+    fn scan_top_down<A>(
+        &self,
+        root_acc: A,
+        mut descend: impl FnMut(&A, &Self) -> A,
+        mut visit: impl FnMut(&A, &Self),
+    ) {
+        fn go<S: Children, A>(
+            node: &S,
+            acc: &A,
+            descend: &mut impl FnMut(&A, &S) -> A,
+            visit: &mut impl FnMut(&A, &S),
+        ) {
+            visit(acc, node);
+            if node.is_leaf() {
+                return;
+            }
+            let acc = descend(acc, node);
+            for child in node.children() {
+                go(child, &acc, descend, visit);
+            }
+        }
+        go(self, &root_acc, &mut descend, &mut visit);
+    }
+
+    /// Renders the structure of the tree as an S-expression, using a function
+    /// to label each node.
+    ///
+    /// A leaf is rendered as just its label, and any other node as its label
+    /// followed by its branches, separated by spaces and surrounded by
+    /// parentheses. This gives stable strings to compare against in tests.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Neg(Box<Expr>),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Num(_) => [None, None],
+    /// #             Neg(e) => [Some(&**e), None],
+    /// #             Add(a, b) => [Some(&**a), Some(&**b)],
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// let tree = Add(Box::new(Num(1)), Box::new(Neg(Box::new(Num(2)))));
+    /// let rendered = tree.debug_structure(|expr| match expr {
+    ///     Num(n) => n.to_string(),
+    ///     Neg(_) => "-".to_owned(),
+    ///     Add(..) => "+".to_owned(),
+    /// });
+    ///
+    /// assert_eq!("(+ 1 (- 2))", rendered);
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn debug_structure(
+        &self,
+        mut label: impl FnMut(&Self) -> String,
+    ) -> String {
+        fn go<S: Children>(
+            node: &S,
+            label: &mut impl FnMut(&S) -> String,
+            out: &mut String,
+        ) {
+            if node.is_leaf() {
+                out.push_str(&label(node));
+                return;
+            }
+            out.push('(');
+            out.push_str(&label(node));
+            for child in node.children() {
+                out.push(' ');
+                go(child, label, out);
+            }
+            out.push(')');
+        }
+        let mut out = String::new();
+        go(self, &mut label, &mut out);
+        out
+    }
+
+    /// Runs a check on every node of the tree in pre-order, collecting a
+    /// diagnostic for each node that fails it.
+    ///
+    /// Unlike a search, this doesn't stop at the first failure. The check is
+    /// also given the path to each node, numbered like in
+    /// [`TreeWalk::bottom_up_with_path`].
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::Children;
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Neg(Box<Expr>),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    ///
+    /// impl Children for Expr {
+    ///     fn children(&self) -> impl Iterator<Item = &Self> {
+    ///         match self {
+    ///             Num(_) => [None, None],
+    ///             Neg(e) => [Some(&**e), None],
+    ///             Add(a, b) => [Some(&**a), Some(&**b)],
+    ///         }
+    ///         .into_iter()
+    ///         .flatten()
+    ///     }
+    /// }
+    ///
+    /// let tree = Add(
+    ///     Box::new(Num(-1)),
+    ///     Box::new(Neg(Box::new(Add(Box::new(Num(2)), Box::new(Num(-3)))))),
+    /// );
+    /// let diagnostics = tree.validate(|path, expr| match expr {
+    ///     Num(n) if *n < 0 => Some((path.to_vec(), "negative literal")),
+    ///     _ => None,
+    /// });
+    ///
+    /// assert_eq!(
+    ///     vec![
+    ///         (vec![0], "negative literal"),
+    ///         (vec![1, 0, 1], "negative literal"),
+    ///     ],
+    ///     diagnostics,
+    /// );
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn validate<D>(
+        &self,
+        mut check: impl FnMut(&[usize], &Self) -> Option<D>,
+    ) -> Vec<D> {
+        fn go<S: Children, D>(
+            node: &S,
+            path: &mut Vec<usize>,
+            check: &mut impl FnMut(&[usize], &S) -> Option<D>,
+            diagnostics: &mut Vec<D>,
+        ) {
+            diagnostics.extend(check(path, node));
+            for (index, child) in node.children().enumerate() {
+                path.push(index);
+                go(child, path, check, diagnostics);
+                path.pop();
+            }
+        }
+        let mut diagnostics = Vec::new();
+        go(self, &mut Vec::new(), &mut check, &mut diagnostics);
+        diagnostics
+    }
+}
+
+/// Trait for tree-like structures that can be hashed by their structure with
+/// [`structural_hash`].
+///
+/// [`structural_hash`]: StructuralHash::structural_hash
+pub trait StructuralHash: Children {
+    /// Feeds the data stored directly in this node, excluding its branches,
+    /// into a [`Hasher`].
+    ///
+    /// This should hash everything that [`Eq`] compares other than the
+    /// branches, such as the kind of node and any values it holds.
+    ///
+    /// [`Hasher`]: core::hash::Hasher
+    /// [`Eq`]: core::cmp::Eq
+    fn hash_payload<H: Hasher>(&self, state: &mut H);
+
+    /// Hashes the whole tree by combining the [`hash_payload`] of every node
+    /// with the shape of the tree.
+    ///
+    /// The hash of a subtree only depends on the subtree itself, not on where
+    /// it appears, which makes it usable as a key for caching or common
+    /// subexpression elimination. For that to work, it has to agree with
+    /// [`Eq`]: trees that are equal must hash equally, which requires
+    /// [`hash_payload`] to be implemented accordingly.
+    ///
+    /// [`hash_payload`]: StructuralHash::hash_payload
+    /// [`Eq`]: core::cmp::Eq
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::hash::{DefaultHasher, Hash, Hasher};
+    /// # use trexp::{Children, StructuralHash};
+    /// #[derive(PartialEq, Eq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    ///
+    /// impl Children for Expr {
+    ///     fn children(&self) -> impl Iterator<Item = &Self> {
+    ///         match self {
+    ///             Num(_) => None,
+    ///             Add(a, b) => Some([&**a, &**b]),
+    ///         }
+    ///         .into_iter()
+    ///         .flatten()
+    ///     }
+    /// }
+    ///
+    /// impl StructuralHash for Expr {
+    ///     fn hash_payload<H: Hasher>(&self, state: &mut H) {
+    ///         match self {
+    ///             Num(n) => (0, n).hash(state),
+    ///             Add(..) => 1.hash(state),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn hash(expr: &Expr) -> u64 {
+    ///     let mut hasher = DefaultHasher::new();
+    ///     expr.structural_hash(&mut hasher);
+    ///     hasher.finish()
+    /// }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let Add(left, right) = add(add(Num(1), Num(2)), add(Num(1), Num(2))) else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(hash(&left), hash(&right));
+    /// assert_ne!(hash(&add(Num(1), Num(2))), hash(&add(Num(2), Num(1))));
+    /// ```
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.hash_payload(state);
+        state.write_usize(self.arity());
+        for child in self.children() {
+            child.structural_hash(state);
+        }
+    }
+}
+
+/// Trait for tree-like structures whose nodes can be compared without
+/// comparing their branches, which is needed for [`diff`].
+///
+/// [`diff`]: TreeDiff::diff
+pub trait TreeDiff: Children {
+    /// Returns `true` if this node holds the same data as `other`, not
+    /// counting their branches.
+    ///
+    /// This should compare everything that [`PartialEq`] would compare other
+    /// than the branches, such as the kind of node and any values it holds.
+    ///
+    /// [`PartialEq`]: core::cmp::PartialEq
+    fn payload_eq(&self, other: &Self) -> bool;
+
+    /// Lists the replacements needed to turn `self` into `other`, as pairs of
+    /// paths and the nodes of `other` to put there.
+    ///
+    /// Paths are numbered like in [`TreeWalk::bottom_up_with_path`] and listed
+    /// in pre-order. Two nodes are descended into if they have the same
+    /// number of children and [`payload_eq`] says that they hold the same
+    /// data, so that only their children can differ. Otherwise, the node from
+    /// `other` replaces the whole subtree, which is also what happens where
+    /// the two trees have different shapes. Each node is only compared once,
+    /// so this takes time linear in the size of the smaller tree, and applying
+    /// the replacements in order turns `self` into `other`.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// [`payload_eq`]: TreeDiff::payload_eq
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Children, TreeDiff};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Neg(Box<Expr>),
+    ///     Add(Box<Expr>, Box<Expr>),
+    ///     Mul(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Num(_) => [None, None],
+    /// #             Neg(e) => [Some(&**e), None],
+    /// #             Add(a, b) | Mul(a, b) => [Some(&**a), Some(&**b)],
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// impl TreeDiff for Expr {
+    ///     fn payload_eq(&self, other: &Self) -> bool {
+    ///         match (self, other) {
+    ///             (Num(a), Num(b)) => a == b,
+    ///             (Neg(_), Neg(_)) | (Add(..), Add(..)) | (Mul(..), Mul(..)) => {
+    ///                 true
+    ///             }
+    ///             _ => false,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// fn mul(a: Expr, b: Expr) -> Expr {
+    ///     Mul(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let old = add(Num(1), add(Num(2), Num(3)));
+    /// let new = add(Num(1), add(Num(2), Num(4)));
+    /// assert_eq!(vec![(vec![1, 1], &Num(4))], old.diff(&new));
+    ///
+    /// let new = add(Num(1), Neg(Box::new(Num(2))));
+    /// assert_eq!(vec![(vec![1], &Neg(Box::new(Num(2))))], old.diff(&new));
+    ///
+    /// // Both the node itself and one of its children differ
+    /// let old = add(Num(1), Num(2));
+    /// let new = mul(Num(1), Num(3));
+    /// assert_eq!(vec![(vec![], &new)], old.diff(&new));
+    ///
+    /// assert!(old.diff(&old).is_empty());
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn diff<'a>(&'a self, other: &'a Self) -> Vec<(Vec<usize>, &'a Self)> {
+        fn go<'a, S: TreeDiff>(
+            old: &S,
+            new: &'a S,
+            path: &mut Vec<usize>,
+            edits: &mut Vec<(Vec<usize>, &'a S)>,
+        ) {
+            if old.arity() != new.arity() || !old.payload_eq(new) {
+                edits.push((path.clone(), new));
+                return;
+            }
+            for (index, (old, new)) in
+                old.children().zip(new.children()).enumerate()
+            {
+                path.push(index);
+                go(old, new, path, edits);
+                path.pop();
+            }
+        }
+        let mut edits = Vec::new();
+        go(self, other, &mut Vec::new(), &mut edits);
+        edits
+    }
+}
+
+/// Finds the deepest node that is an ancestor of (or equal to) the nodes at
+/// both of the given paths, returning it along with its path.
+///
+/// Paths are numbered like in [`TreeWalk::bottom_up_with_path`]. The result is
+/// the node at the longest common prefix of `a` and `b`. If that prefix leads
+/// outside of the tree, the descent stops at the deepest node that exists
+/// along it.
+///
+/// Only available with the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// # use trexp::{lowest_common_ancestor, Children};
+/// #[derive(Debug, PartialEq)]
+/// struct Tree(char, Vec<Tree>);
+///
+/// impl Children for Tree {
+///     fn children(&self) -> impl Iterator<Item = &Self> {
+///         self.1.iter()
+///     }
+/// }
+///
+/// let leaf = |name| Tree(name, vec![]);
+/// let tree = Tree('a', vec![
+///     leaf('b'),
+///     Tree('c', vec![leaf('d'), Tree('e', vec![leaf('f')])]),
+/// ]);
+///
+/// // Nested
+/// let (node, path) = lowest_common_ancestor(&tree, &[1], &[1, 1, 0]);
+/// assert_eq!(('c', vec![1]), (node.0, path));
+///
+/// // Siblings
+/// let (node, path) = lowest_common_ancestor(&tree, &[1, 0], &[1, 1]);
+/// assert_eq!(('c', vec![1]), (node.0, path));
+///
+/// // Identical
+/// let (node, path) = lowest_common_ancestor(&tree, &[1, 1], &[1, 1]);
+/// assert_eq!(('e', vec![1, 1]), (node.0, path));
+///
+/// // Different branches of the root
+/// let (node, path) = lowest_common_ancestor(&tree, &[0], &[1, 0]);
+/// assert_eq!(('a', vec![]), (node.0, path));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn lowest_common_ancestor<'a, S: Children>(
+    tree: &'a S,
+    a: &[usize],
+    b: &[usize],
+) -> (&'a S, Vec<usize>) {
+    let mut node = tree;
+    let mut path = Vec::new();
+    for (&index, _) in a.iter().zip(b).take_while(|(a, b)| a == b) {
+        let Some(child) = node.children().nth(index) else {
+            break;
+        };
+        node = child;
+        path.push(index);
+    }
+    (node, path)
+}
+
+/// Extension methods for trees that can be walked without any effect.
+///
+/// This trait is implemented for every type that implements `TreeWalk<Self>`.
+pub trait TreeTransform: TreeWalk<Self> {
+    /// Applies a function to every node of a tree in a bottom-up manner,
+    /// stopping the entire traversal as soon as the function returns
+    /// [`Break`].
+    ///
+    /// Unlike the [`Result`] effect, which is meant for errors, this is meant
+    /// for returning early with a value once the traversal has found what it
+    /// was looking for. Once the function has returned [`Break`], it is not
+    /// called again and the partially transformed tree is thrown away, with
+    /// [`Default`] standing in for the node that the function consumed.
+    ///
+    /// [`Break`]: core::ops::ControlFlow::Break
+    /// [`Result`]: core::result::Result
+    /// [`Default`]: core::default::Default
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use core::ops::ControlFlow;
+    /// # use trexp::{TreeTransform, TreeWalk};
+    /// #[derive(Debug, Default, PartialEq)]
+    /// enum Expr {
+    ///     #[default]
+    ///     Zero,
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Self> for Expr {
+    /// #     fn each_branch(self, mut f: impl FnMut(Self) -> Self) -> Self {
+    /// #         match self {
+    /// #             Add(a, b) => Add(Box::new(f(*a)), Box::new(f(*b))),
+    /// #             _ => self,
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let tree = || add(add(Num(1), Num(2)), add(Num(3), Num(4)));
+    /// let visit_at_most = |n| {
+    ///     let mut visited = 0;
+    ///     let result = tree().bottom_up_try_break(|expr| {
+    ///         visited += 1;
+    ///         if visited == n {
+    ///             ControlFlow::Break(expr)
+    ///         } else {
+    ///             ControlFlow::Continue(expr)
+    ///         }
+    ///     });
+    ///     (result, visited)
+    /// };
+    ///
+    /// assert_eq!((ControlFlow::Break(Num(2)), 2), visit_at_most(2));
+    /// assert_eq!((ControlFlow::Continue(tree()), 7), visit_at_most(10));
+    /// ```
+    fn bottom_up_try_break<B>(
+        self,
+        mut f: impl FnMut(Self) -> ControlFlow<B, Self>,
+    ) -> ControlFlow<B, Self>
+    where
+        Self: Default,
+    {
+        fn go<S: TreeTransform + Default, B>(
+            branch: S,
+            broken: &mut Option<B>,
+            f: &mut impl FnMut(S) -> ControlFlow<B, S>,
+        ) -> S {
+            let rest_transformed = branch.each_branch(|branch| {
+                if broken.is_some() {
+                    branch
+                } else {
+                    go(branch, broken, f)
+                }
+            });
+            if broken.is_some() {
+                return rest_transformed;
+            }
+            match f(rest_transformed) {
+                ControlFlow::Continue(branch) => branch,
+                ControlFlow::Break(b) => {
+                    *broken = Some(b);
+                    S::default()
+                }
+            }
+        }
+        let mut broken = None;
+        let transformed = go(self, &mut broken, &mut f);
+        broken.map_or(ControlFlow::Continue(transformed), ControlFlow::Break)
+    }
+
+    /// Builds a transformed, owned copy of a borrowed tree, leaving the
+    /// original untouched.
+    ///
+    /// The function is applied bottom-up like with [`bottom_up`], so the node
+    /// it's given already has transformed branches. Every node is cloned
+    /// exactly once, and the function gets the clone by value, so it can
+    /// reuse the parts it wants to keep instead of cloning them again.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{TreeTransform, TreeWalk};
+    /// #[derive(Debug, Clone, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Self> for Expr {
+    /// #     fn each_branch(self, mut f: impl FnMut(Self) -> Self) -> Self {
+    /// #         match self {
+    /// #             Num(_) => self,
+    /// #             Add(a, b) => Add(Box::new(f(*a)), Box::new(f(*b))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn double(expr: Expr) -> Expr {
+    ///     match expr {
+    ///         Num(n) => Num(n * 2),
+    ///         _ => expr,
+    ///     }
+    /// }
+    ///
+    /// let arena = [Add(Box::new(Num(1)), Box::new(Num(2)))];
+    /// let borrowed: &Expr = &arena[0];
+    ///
+    /// assert_eq!(
+    ///     Add(Box::new(Num(2)), Box::new(Num(4))),
+    ///     borrowed.to_owned_transformed(double),
+    /// );
+    /// assert_eq!(Add(Box::new(Num(1)), Box::new(Num(2))), arena[0]);
+    /// ```
+    #[must_use]
+    fn to_owned_transformed(&self, f: impl FnMut(Self) -> Self) -> Self
+    where
+        Self: Clone,
+    {
+        self.clone().bottom_up(f)
+    }
+}
+
+impl<T: TreeWalk<Self>> TreeTransform for T {}
+
+/// Extension methods for trees that can be walked with the [`Rewrite`] effect.
+///
+/// This trait is implemented for every type that implements
+/// `TreeWalk<Rewrite<Self>>`.
+///
+/// [`Rewrite`]: crate::Rewrite
+pub trait TreeRewrite: TreeWalk<Rewrite<Self>> {
+    /// Applies a function top-down, but stops descending into a node as soon
+    /// as the function returns [`Dirty`] for it.
+    ///
+    /// This is the "outermost" rewriting strategy: a node that was rewritten
+    /// keeps its branches as they are until the next pass, which prevents
+    /// rules from firing twice on the same part of the tree in a single pass.
+    /// Nodes for which the function returns [`Clean`] are descended into like
+    /// with [`top_down`].
+    ///
+    /// [`Clean`]: crate::Rewrite::Clean
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    /// [`top_down`]: TreeWalk::top_down
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    ///
+    /// impl TreeWalk<Rewrite<Self>> for Expr {
+    ///     fn each_branch(
+    ///         self,
+    ///         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ///     ) -> Rewrite<Self> {
+    ///         match self {
+    ///             Num(_) => Clean(self),
+    ///             Add(a, b) => f(*a)
+    ///                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// fn swap(expr: Expr) -> Rewrite<Expr> {
+    ///     match expr {
+    ///         Add(a, b) => Dirty(Add(b, a)),
+    ///         _ => Clean(expr),
+    ///     }
+    /// }
+    ///
+    /// let tree = || add(add(Num(1), Num(2)), Num(3));
+    ///
+    /// assert_eq!(Dirty(add(Num(3), add(Num(2), Num(1)))), tree().top_down(swap));
+    /// assert_eq!(Dirty(add(Num(3), add(Num(1), Num(2)))), tree().top_down_once(swap));
+    /// ```
+    fn top_down_once(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self> {
+        fn go<S: TreeRewrite>(
+            branch: S,
+            f: &mut impl FnMut(S) -> Rewrite<S>,
+        ) -> Rewrite<S> {
+            match f(branch) {
+                Clean(branch) => branch.each_branch(|branch| go(branch, f)),
+                Dirty(branch) => Dirty(branch),
+            }
+        }
+        go(self, &mut f)
+    }
+
+    /// A version of [`bottom_up`] that also returns the paths of all nodes for
+    /// which the function returned [`Dirty`], in the order that they were
+    /// visited.
+    ///
+    /// See [`bottom_up_with_path`] for how paths are numbered.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`bottom_up_with_path`]: TreeWalk::bottom_up_with_path
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Num(_) => Clean(self),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let tree = add(Num(1), add(Num(2), Num(3)));
+    /// let (result, changed) = tree.bottom_up_tracked(|expr| match expr {
+    ///     Num(2) => Dirty(Num(20)),
+    ///     _ => Clean(expr),
+    /// });
+    ///
+    /// assert_eq!(Dirty(add(Num(1), add(Num(20), Num(3)))), result);
+    /// assert_eq!(vec![vec![1, 0]], changed);
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn bottom_up_tracked(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> (Rewrite<Self>, Vec<Vec<usize>>) {
+        let mut changed = Vec::new();
+        let transformed = self.bottom_up_with_path(|path, branch| {
+            let transformed = f(branch);
+            if transformed.is_dirty() {
+                changed.push(path.to_vec());
+            }
+            transformed
+        });
+        (transformed, changed)
+    }
+
+    /// A version of [`bottom_up`] that reports what it does to [`tracing`].
+    ///
+    /// The function is called inside a `TRACE`-level span named `node` for
+    /// every node, and an event is emitted inside that span whenever it
+    /// returns [`Dirty`]. Both carry the path of the node as a `path` field,
+    /// in the same form as for [`bottom_up_with_path`]. Code that doesn't
+    /// enable the feature doesn't pay anything, since [`bottom_up`] itself is
+    /// unaffected by it.
+    ///
+    /// Only available with the `tracing` feature.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`bottom_up_with_path`]: TreeWalk::bottom_up_with_path
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// # use std::{
+    /// #     fmt::Debug,
+    /// #     sync::{
+    /// #         atomic::{AtomicU64, Ordering},
+    /// #         Arc, Mutex,
+    /// #     },
+    /// # };
+    /// # use tracing::{
+    /// #     field::{Field, Visit},
+    /// #     span, Event, Metadata, Subscriber,
+    /// # };
+    /// #
+    /// # #[derive(Default)]
+    /// # struct Capture {
+    /// #     next_span: AtomicU64,
+    /// #     spans: AtomicU64,
+    /// #     events: Arc<Mutex<Vec<String>>>,
+    /// # }
+    /// #
+    /// # struct PathVisitor(String);
+    /// #
+    /// # impl Visit for PathVisitor {
+    /// #     fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+    /// #         if field.name() == "path" {
+    /// #             self.0 = format!("{value:?}");
+    /// #         }
+    /// #     }
+    /// # }
+    /// #
+    /// # impl Subscriber for Capture {
+    /// #     fn enabled(&self, _: &Metadata<'_>) -> bool {
+    /// #         true
+    /// #     }
+    /// #     fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+    /// #         self.spans.fetch_add(1, Ordering::Relaxed);
+    /// #         span::Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed) + 1)
+    /// #     }
+    /// #     fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+    /// #     fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+    /// #     fn event(&self, event: &Event<'_>) {
+    /// #         let mut visitor = PathVisitor(String::new());
+    /// #         event.record(&mut visitor);
+    /// #         self.events.lock().unwrap().push(visitor.0);
+    /// #     }
+    /// #     fn enter(&self, _: &span::Id) {}
+    /// #     fn exit(&self, _: &span::Id) {}
+    /// # }
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Num(_) => Clean(self),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// fn fold_constants(expr: Expr) -> Rewrite<Expr> {
+    ///     match expr {
+    ///         Add(a, b) => match (*a, *b) {
+    ///             (Num(a), Num(b)) => Dirty(Num(a + b)),
+    ///             (a, b) => Clean(add(a, b)),
+    ///         },
+    ///         _ => Clean(expr),
+    ///     }
+    /// }
+    ///
+    /// // A subscriber that records the `path` field of every event
+    /// let capture = Capture::default();
+    /// let events = Arc::clone(&capture.events);
+    /// # let capture = Arc::new(capture);
+    /// # let spans = Arc::clone(&capture);
+    ///
+    /// let tree = add(Num(1), add(Num(2), Num(3)));
+    /// let result = tracing::subscriber::with_default(capture, || {
+    ///     tree.bottom_up_traced(fold_constants)
+    /// });
+    ///
+    /// assert_eq!(Dirty(Num(6)), result);
+    /// assert_eq!(vec!["[1]", "[]"], *events.lock().unwrap());
+    /// # assert_eq!(5, spans.spans.load(Ordering::Relaxed)); // One per node
+    /// ```
+    #[cfg(feature = "tracing")]
+    fn bottom_up_traced(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self> {
+        self.bottom_up_with_path(|path, branch| {
+            let _span = tracing::trace_span!("node", ?path).entered();
+            f(branch).inspect_dirty(|_| tracing::trace!(?path, "rule fired"))
+        })
+    }
+
+    /// A version of [`normalize`] that reports every pass to [`tracing`] like
+    /// [`bottom_up_traced`].
+    ///
+    /// Only available with the `tracing` feature.
+    ///
+    /// [`normalize`]: TreeRewrite::normalize
+    /// [`bottom_up_traced`]: TreeRewrite::bottom_up_traced
+    #[cfg(feature = "tracing")]
+    fn normalize_traced(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self> {
+        Rewrite::repeat(self, |tree| tree.bottom_up_traced(&mut f))
+    }
+
+    /// A version of [`bottom_up`] that transforms each distinct subtree only
+    /// once, reusing the result for every other subtree that is equal to it.
+    ///
+    /// Results are cached by the subtree as it was *before* being transformed,
+    /// so this only helps when the tree contains subtrees that compare equal,
+    /// such as in expression DAGs that have been expanded into trees. The cache
+    /// only lives for the duration of a single call. Hashing and cloning every
+    /// subtree has a cost of its own, so this is slower than [`bottom_up`]
+    /// when duplicates are rare.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Num(_) => Clean(self),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let mut fired = 0;
+    /// let tree = add(add(Num(1), Num(2)), add(Num(1), Num(2)));
+    /// let folded = tree.bottom_up_cached(|expr| {
+    ///     fired += 1;
+    ///     match expr {
+    ///         Add(a, b) => match (*a, *b) {
+    ///             (Num(a), Num(b)) => Dirty(Num(a + b)),
+    ///             (a, b) => Clean(add(a, b)),
+    ///         },
+    ///         _ => Clean(expr),
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(Dirty(Num(6)), folded);
+    /// assert_eq!(4, fired); // `1`, `2`, `1 + 2` and the root
+    /// ```
+    #[cfg(feature = "std")]
+    fn bottom_up_cached(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self>
+    where
+        Self: Hash + Eq + Clone,
+    {
+        fn go<S: TreeRewrite + Hash + Eq + Clone>(
+            branch: S,
+            cache: &mut HashMap<S, Rewrite<S>>,
+            f: &mut impl FnMut(S) -> Rewrite<S>,
+        ) -> Rewrite<S> {
+            if let Some(cached) = cache.get(&branch) {
+                return cached.clone();
+            }
+            let key = branch.clone();
+            let transformed = branch
+                .each_branch(|branch| go(branch, cache, f))
+                .bind(&mut *f);
+            cache.insert(key, transformed.clone());
+            transformed
+        }
+        go(self, &mut HashMap::new(), &mut f)
+    }
+
+    /// A version of [`bottom_up`] that stops applying the function once it has
+    /// returned [`Dirty`] `max_changes` times.
+    ///
+    /// All nodes after that point are left as they are. Returns the
+    /// transformed tree along with the number of changes that were made, which
+    /// is at most `max_changes`.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Num(_) => Clean(self),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let double = |expr| match expr {
+    ///     Num(n) => Dirty(Num(n * 2)),
+    ///     _ => Clean(expr),
+    /// };
+    ///
+    /// assert_eq!(
+    ///     (Dirty(add(Num(2), add(Num(2), Num(3)))), 1),
+    ///     add(Num(1), add(Num(2), Num(3))).bottom_up_limited_changes(1, double),
+    /// );
+    /// assert_eq!(
+    ///     (Dirty(add(Num(2), Num(4))), 2),
+    ///     add(Num(1), Num(2)).bottom_up_limited_changes(5, double),
+    /// );
+    /// ```
+    fn bottom_up_limited_changes(
+        self,
+        max_changes: usize,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> (Rewrite<Self>, usize) {
+        let mut changes = 0;
+        let transformed = self.bottom_up(|branch| {
+            if changes >= max_changes {
+                return Clean(branch);
+            }
+            let transformed = f(branch);
+            if transformed.is_dirty() {
+                changes += 1;
+            }
+            transformed
+        });
+        (transformed, changes)
+    }
+
+    /// Repeats bottom-up passes over the tree until a pass doesn't change
+    /// anything.
+    ///
+    /// This never returns if the function keeps returning [`Dirty`] forever;
+    /// see [`normalize_bounded`] for a version that gives up eventually.
+    ///
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    /// [`normalize_bounded`]: TreeRewrite::normalize_bounded
+    fn normalize(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self> {
+        Rewrite::repeat(self, |tree| tree.bottom_up(&mut f))
+    }
+
+    /// A version of [`normalize`] for re-running a rewriter after editing a
+    /// tree that was already normalized, which skips the parts of the tree
+    /// that haven't changed since.
+    ///
+    /// Every pass walks the tree together with `previous`, pairing up nodes by
+    /// their position among their siblings like [`zip_bottom_up`]. A subtree
+    /// that is equal to its counterpart in `previous` is treated as [`Clean`]
+    /// without calling the function on it or descending into it. `previous`
+    /// should therefore be a normal form of the same function, such as the
+    /// result of an earlier call to [`normalize`], and the function should
+    /// only depend on the node it's given.
+    ///
+    /// The two trees are assumed to mostly have the same shape. Where they
+    /// don't, the branches of a node that have no counterpart in `previous`
+    /// are walked like with [`bottom_up`], and extra branches in `previous`
+    /// are ignored, so the result is the same as with [`normalize`] either way.
+    /// Note that comparing a subtree to its counterpart takes time
+    /// proportional to its size, so this is mostly useful when the function
+    /// is much more expensive than an equality check.
+    ///
+    /// [`normalize`]: TreeRewrite::normalize
+    /// [`zip_bottom_up`]: TreeWalk::zip_bottom_up
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`Clean`]: crate::Rewrite::Clean
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Children, Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, Clone, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Var(char),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #             _ => Clean(self),
+    /// #         }
+    /// #     }
+    /// # }
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Add(a, b) => Some([&**a, &**b]),
+    /// #             _ => None,
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// fn fold_constants(calls: &mut usize, expr: Expr) -> Rewrite<Expr> {
+    ///     *calls += 1;
+    ///     match expr {
+    ///         Add(a, b) => match (*a, *b) {
+    ///             (Num(a), Num(b)) => Dirty(Num(a + b)),
+    ///             (a, b) => Clean(add(a, b)),
+    ///         },
+    ///         _ => Clean(expr),
+    ///     }
+    /// }
+    ///
+    /// let previous = add(add(Var('x'), Num(3)), add(Var('y'), Num(4)));
+    /// let edited = add(add(Var('x'), Num(3)), add(Var('y'), add(Num(1), Num(3))));
+    ///
+    /// let mut normalize_calls = 0;
+    /// let normalized = edited
+    ///     .clone()
+    ///     .normalize(|expr| fold_constants(&mut normalize_calls, expr));
+    ///
+    /// let mut incremental_calls = 0;
+    /// let incremental = edited.incremental_normalize(&previous, |expr| {
+    ///     fold_constants(&mut incremental_calls, expr)
+    /// });
+    ///
+    /// assert_eq!(Dirty(previous), incremental);
+    /// assert_eq!(normalized, incremental);
+    /// // Only `1`, `3`, `1 + 3`, `y + 4` and the root were visited.
+    /// assert_eq!(5, incremental_calls);
+    /// assert!(incremental_calls < normalize_calls);
+    /// ```
+    fn incremental_normalize(
+        self,
+        previous: &Self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self>
+    where
+        Self: Children + PartialEq,
+    {
+        fn go<S: TreeWalk<Rewrite<S>> + Children + PartialEq>(
+            branch: S,
+            previous: Option<&S>,
+            f: &mut impl FnMut(S) -> Rewrite<S>,
+        ) -> Rewrite<S> {
+            if previous.is_some_and(|previous| *previous == branch) {
+                return Clean(branch);
+            }
+            let mut previous_branches = previous.map(Children::children);
+            let rest_transformed = branch.each_branch(|branch| {
+                let previous =
+                    previous_branches.as_mut().and_then(Iterator::next);
+                go(branch, previous, f)
+            });
+            Bind::bind_mut(rest_transformed, f)
+        }
+
+        Rewrite::repeat(self, |tree| go(tree, Some(previous), &mut f))
+    }
+
+    /// A version of [`normalize`] that only revisits the parts of the tree
+    /// that could have changed since the previous pass.
+    ///
+    /// The first pass goes over the whole tree like [`bottom_up`], remembering
+    /// the path of every node for which the function returned [`Dirty`]. Each
+    /// later pass only walks the subtrees at those paths, and then applies the
+    /// function to their ancestors from the bottom up, stopping at the first
+    /// ancestor whose branches all came back [`Clean`]. This continues until a
+    /// pass doesn't change anything.
+    ///
+    /// This gives the same result as [`normalize`] as long as the function
+    /// only depends on the node it's given, including its branches, and not on
+    /// where the node is in the tree or on any state that changes between
+    /// calls. Under that assumption, a node that the function left alone in one
+    /// pass and whose subtree hasn't changed since would be left alone again,
+    /// so skipping it is safe. Paths are numbered like in
+    /// [`TreeWalk::bottom_up_with_path`].
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// [`normalize`]: TreeRewrite::normalize
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`Clean`]: crate::Rewrite::Clean
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Num(_) => Clean(self),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// fn count_down(calls: &mut usize, expr: Expr) -> Rewrite<Expr> {
+    ///     *calls += 1;
+    ///     match expr {
+    ///         Num(n) if n > 0 => Dirty(Num(n - 1)),
+    ///         Add(a, b) if *a == Num(0) => Dirty(*b),
+    ///         _ => Clean(expr),
+    ///     }
+    /// }
+    ///
+    /// let tree = || add(Num(3), add(add(Num(0), Num(0)), add(Num(0), Num(0))));
+    ///
+    /// let mut normalize_calls = 0;
+    /// let normalized = tree().normalize(|expr| count_down(&mut normalize_calls, expr));
+    ///
+    /// let mut worklist_calls = 0;
+    /// let worklisted =
+    ///     tree().normalize_worklist(|expr| count_down(&mut worklist_calls, expr));
+    ///
+    /// assert_eq!(Dirty(Num(0)), normalized);
+    /// assert_eq!(normalized, worklisted);
+    /// assert!(worklist_calls < normalize_calls);
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn normalize_worklist(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self> {
+        fn apply<S>(
+            branch: S,
+            path: &[usize],
+            f: &mut impl FnMut(S) -> Rewrite<S>,
+            dirty: &mut Vec<Vec<usize>>,
+        ) -> Rewrite<S> {
+            let transformed = f(branch);
+            if transformed.is_dirty() {
+                dirty.push(path.to_vec());
+            }
+            transformed
+        }
+
+        fn whole<S: TreeRewrite>(
+            branch: S,
+            path: &mut Vec<usize>,
+            f: &mut impl FnMut(S) -> Rewrite<S>,
+            dirty: &mut Vec<Vec<usize>>,
+        ) -> Rewrite<S> {
+            let mut index = 0;
+            let rest_transformed = branch.each_branch(|branch| {
+                path.push(index);
+                index += 1;
+                let transformed = whole(branch, path, f, dirty);
+                path.pop();
+                transformed
+            });
+            rest_transformed.bind(|branch| apply(branch, path, f, dirty))
+        }
+
+        // `targets` is sorted, has no path that is a prefix of another and
+        // only contains paths that start with `path`.
+        fn partial<S: TreeRewrite>(
+            branch: S,
+            path: &mut Vec<usize>,
+            targets: &[Vec<usize>],
+            f: &mut impl FnMut(S) -> Rewrite<S>,
+            dirty: &mut Vec<Vec<usize>>,
+        ) -> Rewrite<S> {
+            let depth = path.len();
+            if targets.iter().any(|target| target.len() == depth) {
+                return whole(branch, path, f, dirty);
+            }
+            let mut index = 0;
+            let rest_transformed = branch.each_branch(|branch| {
+                let start = targets.partition_point(|t| t[depth] < index);
+                let end = targets.partition_point(|t| t[depth] <= index);
+                let transformed = if start == end {
+                    Clean(branch)
+                } else {
+                    path.push(index);
+                    let transformed =
+                        partial(branch, path, &targets[start..end], f, dirty);
+                    path.pop();
+                    transformed
+                };
+                index += 1;
+                transformed
+            });
+            match rest_transformed {
+                Clean(branch) => Clean(branch),
+                Dirty(branch) => {
+                    Dirty(apply(branch, path, f, dirty).into_inner())
+                }
+            }
+        }
+
+        let mut tree = Clean(self);
+        let mut targets = Vec::from([Vec::new()]);
+        while !targets.is_empty() {
+            let mut dirty = Vec::new();
+            tree = tree.bind(|tree| {
+                partial(tree, &mut Vec::new(), &targets, &mut f, &mut dirty)
+            });
+            dirty.sort_unstable();
+            dirty.dedup_by(|path, kept| path.starts_with(kept));
+            targets = dirty;
+        }
+        tree
+    }
+
+    /// A version of [`normalize`] that gives up after `max_passes` passes.
+    ///
+    /// Returns `Ok` with the normalized tree if a pass didn't change anything
+    /// within the limit, or `Err` with the tree as it was after the last pass
+    /// otherwise. This guards against rules that loop forever.
+    ///
+    /// [`normalize`]: TreeRewrite::normalize
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Neg(Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Num(_) => Clean(self),
+    /// #             Neg(e) => f(*e).map(|e| Neg(Box::new(e))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// let fold_neg = |expr| match expr {
+    ///     Neg(e) => match *e {
+    ///         Num(n) => Dirty(Num(-n)),
+    ///         e => Clean(Neg(Box::new(e))),
+    ///     },
+    ///     _ => Clean(expr),
+    /// };
+    /// let tree = Neg(Box::new(Neg(Box::new(Num(1)))));
+    /// assert_eq!(Ok(Num(1)), tree.normalize_bounded(10, fold_neg));
+    ///
+    /// // Never stops incrementing
+    /// let increment = |expr| match expr {
+    ///     Num(n) => Dirty(Num(n + 1)),
+    ///     _ => Clean(expr),
+    /// };
+    /// assert_eq!(Err(Num(3)), Num(0).normalize_bounded(3, increment));
+    /// ```
+    fn normalize_bounded(
+        self,
+        max_passes: usize,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Result<Self, Self> {
+        Rewrite::repeat_bounded(self, max_passes, |tree| tree.bottom_up(&mut f))
+    }
+
+    /// Returns `true` if the two trees are equal after both have been
+    /// normalized with the same function.
+    ///
+    /// See [`normalize`] for how trees are normalized.
+    ///
+    /// [`normalize`]: TreeRewrite::normalize
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Var(char),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Var(_) => Clean(self),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// // (a + b) + c => a + (b + c)
+    /// let associate_right = |expr| match expr {
+    ///     Add(ab, c) => match *ab {
+    ///         Add(a, b) => Dirty(add(*a, add(*b, *c))),
+    ///         ab => Clean(Add(Box::new(ab), c)),
+    ///     },
+    ///     _ => Clean(expr),
+    /// };
+    ///
+    /// let left = add(add(Var('a'), Var('b')), Var('c'));
+    /// let right = add(Var('a'), add(Var('b'), Var('c')));
+    /// assert!(left.eq_normalized(right, associate_right));
+    ///
+    /// let swapped = add(Var('b'), add(Var('a'), Var('c')));
+    /// let left = add(add(Var('a'), Var('b')), Var('c'));
+    /// assert!(!left.eq_normalized(swapped, associate_right));
+    /// ```
+    fn eq_normalized(
+        self,
+        other: Self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> bool
+    where
+        Self: PartialEq,
+    {
+        self.normalize(&mut f).into_inner() == other.normalize(f).into_inner()
+    }
+
+    /// Simplifies a tree as much as possible using a set of rules.
+    ///
+    /// At every node, the rules are tried in order until one of them fires.
+    /// Whole bottom-up passes over the tree are then repeated until a pass
+    /// doesn't change anything.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, Rule, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Var(char),
+    ///     Add(Box<Expr>, Box<Expr>),
+    ///     Mul(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #             Mul(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Mul(Box::new(a), Box::new(b)))),
+    /// #             _ => Clean(self),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// fn mul(a: Expr, b: Expr) -> Expr {
+    ///     Mul(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// fn fold_add(expr: Expr) -> Rewrite<Expr> {
+    ///     match expr {
+    ///         Add(a, b) => match (*a, *b) {
+    ///             (Num(a), Num(b)) => Dirty(Num(a + b)),
+    ///             (a, b) => Clean(add(a, b)),
+    ///         },
+    ///         _ => Clean(expr),
+    ///     }
+    /// }
+    ///
+    /// fn fold_mul(expr: Expr) -> Rewrite<Expr> {
+    ///     match expr {
+    ///         Mul(a, b) => match (*a, *b) {
+    ///             (Num(a), Num(b)) => Dirty(Num(a * b)),
+    ///             (a, b) => Clean(mul(a, b)),
+    ///         },
+    ///         _ => Clean(expr),
+    ///     }
+    /// }
+    ///
+    /// fn add_zero(expr: Expr) -> Rewrite<Expr> {
+    ///     match expr {
+    ///         Add(a, b) if *b == Num(0) => Dirty(*a),
+    ///         _ => Clean(expr),
+    ///     }
+    /// }
+    ///
+    /// let mut rules: [Box<dyn Rule<Expr>>; 3] =
+    ///     [Box::new(fold_add), Box::new(fold_mul), Box::new(add_zero)];
+    ///
+    /// // x + (2 * 3 + -6)
+    /// let tree = add(Var('x'), add(mul(Num(2), Num(3)), Num(-6)));
+    /// assert_eq!(Var('x'), tree.simplify(&mut rules));
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn simplify(self, rules: &mut [Box<dyn Rule<Self> + '_>]) -> Self {
+        let mut first_match = |node| {
+            rules.iter_mut().fold(Clean(node), |node, rule| {
+                node.or_else(|node| rule.apply(node))
+            })
+        };
+        self.normalize(&mut first_match).into_inner()
+    }
+
+    /// A version of [`bottom_up`] that checks that the function never changes
+    /// the number of branches of a node.
+    ///
+    /// This is meant for catching bugs in transformations that should preserve
+    /// arity, such as rules that rewrite operands without adding or removing
+    /// any. The check is only performed when debug assertions are enabled,
+    /// making this equivalent to [`bottom_up`] otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if debug assertions are enabled and the function returns a node
+    /// with a different number of branches than the node it was given.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Children, Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Neg(Box<Expr>),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Num(_) => Clean(self),
+    /// #             Neg(e) => f(*e).map(|e| Neg(Box::new(e))),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Num(_) => [None, None],
+    /// #             Neg(e) => [Some(&**e), None],
+    /// #             Add(a, b) => [Some(&**a), Some(&**b)],
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// let tree = || Add(Box::new(Num(1)), Box::new(Num(2)));
+    ///
+    /// // Preserves arity
+    /// let swapped = tree().bottom_up_checked(|expr| match expr {
+    ///     Add(a, b) => Dirty(Add(b, a)),
+    ///     _ => Clean(expr),
+    /// });
+    /// assert_eq!(Dirty(Add(Box::new(Num(2)), Box::new(Num(1)))), swapped);
+    ///
+    /// // Drops an operand
+    /// let checked = std::panic::catch_unwind(|| {
+    ///     tree().bottom_up_checked(|expr| match expr {
+    ///         Add(a, _) => Dirty(Neg(a)),
+    ///         _ => Clean(expr),
+    ///     })
+    /// });
+    /// if let Ok(negated) = checked {
+    ///     // Debug assertions are disabled
+    ///     assert_eq!(Dirty(Neg(Box::new(Num(1)))), negated);
+    /// }
+    /// ```
+    fn bottom_up_checked(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self>
+    where
+        Self: Children,
+    {
+        self.bottom_up(|branch| {
+            #[cfg(debug_assertions)]
+            let arity = branch.children().count();
+            let transformed = f(branch);
+            #[cfg(debug_assertions)]
+            assert_eq!(
+                arity,
+                transformed.inner_ref().children().count(),
+                "rewrite changed the number of branches of a node",
+            );
+            transformed
+        })
+    }
+
+    /// Replaces the subtree at the given path with `replacement`.
+    ///
+    /// Paths are numbered like in [`TreeWalk::bottom_up_with_path`]. Returns
+    /// [`Dirty`] with the new tree if there is a node at the path, or
+    /// [`Clean`] with the original tree if there isn't.
+    ///
+    /// [`Clean`]: crate::Rewrite::Clean
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Num(_) => Clean(self),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let tree = || add(Num(1), add(Num(2), Num(3)));
+    ///
+    /// assert_eq!(Dirty(Num(0)), tree().splice(&[], Num(0)));
+    /// assert_eq!(
+    ///     Dirty(add(Num(1), add(Num(0), Num(3)))),
+    ///     tree().splice(&[1, 0], Num(0)),
+    /// );
+    /// assert_eq!(Clean(tree()), tree().splice(&[0, 1], Num(0)));
+    /// ```
+    fn splice(self, path: &[usize], replacement: Self) -> Rewrite<Self> {
+        self.replace_subtree(path, |_| Some(replacement))
+    }
+
+    /// Replaces the subtree at the given path with the result of a function,
+    /// unless the function returns `None`.
+    ///
+    /// The function is given a reference to the old subtree so that it can
+    /// decide whether to replace it. Paths are numbered like in
+    /// [`TreeWalk::bottom_up_with_path`]. Returns [`Dirty`] with the new tree
+    /// if the subtree was replaced, or [`Clean`] with the original tree if
+    /// the function returned `None` or there is no node at the path, in which
+    /// case the function isn't called at all.
+    ///
+    /// [`Clean`]: crate::Rewrite::Clean
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Num(_) => Clean(self),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let zero_if_negative = |expr: &Expr| match expr {
+    ///     Num(n) if *n < 0 => Some(Num(0)),
+    ///     _ => None,
+    /// };
+    /// let tree = || add(Num(-1), Num(2));
+    ///
+    /// assert_eq!(
+    ///     Dirty(add(Num(0), Num(2))),
+    ///     tree().replace_subtree(&[0], zero_if_negative),
+    /// );
+    /// assert_eq!(Clean(tree()), tree().replace_subtree(&[1], zero_if_negative));
+    /// ```
+    fn replace_subtree(
+        self,
+        at: &[usize],
+        new: impl FnOnce(&Self) -> Option<Self>,
+    ) -> Rewrite<Self> {
+        fn go<S: TreeRewrite>(
+            branch: S,
+            path: &[usize],
+            new: &mut Option<impl FnOnce(&S) -> Option<S>>,
+        ) -> Rewrite<S> {
+            let Some((&target, path)) = path.split_first() else {
+                return new
+                    .take()
+                    .and_then(|new| new(&branch))
+                    .map_or(Clean(branch), Dirty);
+            };
+            let mut index = 0;
+            branch.each_branch(|branch| {
+                let transformed = if index == target {
+                    go(branch, path, new)
+                } else {
+                    Clean(branch)
+                };
+                index += 1;
+                transformed
+            })
+        }
+        go(self, at, &mut Some(new))
+    }
+
+    /// Wraps every node that matches a predicate using `wrap`, going
+    /// bottom-up.
+    ///
+    /// The result is [`Dirty`] if any node was wrapped. Each node is only
+    /// looked at once, so the nodes created by `wrap` are never wrapped again
+    /// even if they match the predicate themselves.
+    ///
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Call(&'static str, Box<Expr>),
+    ///     Log(Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    ///
+    /// impl TreeWalk<Rewrite<Self>> for Expr {
+    ///     fn each_branch(
+    ///         self,
+    ///         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ///     ) -> Rewrite<Self> {
+    ///         match self {
+    ///             Num(_) => Clean(self),
+    ///             Call(name, arg) => f(*arg).map(|arg| Call(name, Box::new(arg))),
+    ///             Log(e) => f(*e).map(|e| Log(Box::new(e))),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn call(name: &'static str, arg: Expr) -> Expr {
+    ///     Call(name, Box::new(arg))
+    /// }
+    ///
+    /// fn log(e: Expr) -> Expr {
+    ///     Log(Box::new(e))
+    /// }
+    ///
+    /// let tree = call("f", call("g", Num(1)));
+    /// let is_call = |expr: &Expr| matches!(expr, Call(..));
+    ///
+    /// assert_eq!(
+    ///     Dirty(log(call("f", log(call("g", Num(1)))))),
+    ///     tree.wrap_where(is_call, log),
+    /// );
+    /// assert_eq!(Clean(Num(1)), Num(1).wrap_where(is_call, log));
+    /// ```
+    fn wrap_where(
+        self,
+        mut pred: impl FnMut(&Self) -> bool,
+        mut wrap: impl FnMut(Self) -> Self,
+    ) -> Rewrite<Self> {
+        self.bottom_up(|branch| {
+            if pred(&branch) {
+                Dirty(wrap(branch))
+            } else {
+                Clean(branch)
+            }
+        })
+    }
+
+    /// Replaces every subtree that is equal to one of the patterns in
+    /// `mapping` with the corresponding replacement, going bottom-up.
+    ///
+    /// Matching is exact structural equality as given by [`PartialEq`], not
+    /// unification, so patterns can't contain variables. If several patterns
+    /// are equal to a subtree, the first one wins. Replacements are not
+    /// searched for further matches. The result is [`Dirty`] if anything was
+    /// replaced.
+    ///
+    /// [`Dirty`]: crate::Rewrite::Dirty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, Clone, PartialEq)]
+    /// enum Expr {
+    ///     Num(i32),
+    ///     Var(char),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #             _ => Clean(self),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// fn add(a: Expr, b: Expr) -> Expr {
+    ///     Add(Box::new(a), Box::new(b))
+    /// }
+    ///
+    /// let mapping = [
+    ///     (Var('x'), Num(1)),
+    ///     (add(Num(2), Num(3)), Num(5)),
+    /// ];
+    /// let tree = add(Var('x'), add(Var('y'), add(Num(2), Num(3))));
+    ///
+    /// assert_eq!(
+    ///     Dirty(add(Num(1), add(Var('y'), Num(5)))),
+    ///     tree.replace_all(&mapping),
+    /// );
+    /// assert_eq!(Clean(Var('y')), Var('y').replace_all(&mapping));
+    /// ```
+    fn replace_all(self, mapping: &[(Self, Self)]) -> Rewrite<Self>
+    where
+        Self: PartialEq + Clone,
+    {
+        self.bottom_up(|branch| {
+            mapping
+                .iter()
+                .find(|(pattern, _)| *pattern == branch)
+                .map_or(Clean(branch), |(_, replacement)| {
+                    Dirty(replacement.clone())
+                })
+        })
+    }
+
+    /// A version of [`bottom_up`] that walks chains of nodes with a single
+    /// branch each in a loop instead of recursing into them.
+    ///
+    /// The result is the same as with [`bottom_up`], and the function is still
+    /// applied to every node. The only difference is that a chain of unary
+    /// nodes, such as a long series of wrappers, uses a constant amount of
+    /// stack space, so it can't overflow the stack however long it is. This
+    /// is purely about stack safety: taking nodes apart and putting them back
+    /// together makes walking a chain somewhat slower than with [`bottom_up`]
+    /// (see the `bottom_up_iterative_spine/chain` benchmark).
+    ///
+    /// Note that no part of a chain is skipped, not even one whose branches
+    /// all came back [`Clean`]. The function may still fire on the nodes
+    /// above such a branch, so skipping them would change the result.
+    ///
+    /// To walk a chain without recursing, each node in it is taken apart by
+    /// calling [`each_branch`] with a function that swaps its branch out for a
+    /// [`Default`] placeholder, and put back together by calling
+    /// [`each_branch`] again on the result once the branch has been
+    /// transformed. [`each_branch`] must therefore visit the placeholder just
+    /// like it visited the original branch, and [`children`] must agree with
+    /// it about which nodes have a single branch.
+    ///
+    /// Only available with the `alloc` feature.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`each_branch`]: TreeWalk::each_branch
+    /// [`children`]: Children::children
+    /// [`Default`]: core::default::Default
+    /// [`Clean`]: crate::Rewrite::Clean
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Children, Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, Default, PartialEq)]
+    /// enum Expr {
+    ///     #[default]
+    ///     Zero,
+    ///     Succ(Box<Expr>),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    /// # use Expr::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Expr {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Zero => Clean(self),
+    /// #             Succ(e) => f(*e).map(|e| Succ(Box::new(e))),
+    /// #             Add(a, b) => f(*a)
+    /// #                 .bind(|a| f(*b).map(|b| Add(Box::new(a), Box::new(b)))),
+    /// #         }
+    /// #     }
+    /// # }
+    /// # impl Children for Expr {
+    /// #     fn children(&self) -> impl Iterator<Item = &Self> {
+    /// #         match self {
+    /// #             Zero => [None, None],
+    /// #             Succ(e) => [Some(&**e), None],
+    /// #             Add(a, b) => [Some(&**a), Some(&**b)],
+    /// #         }
+    /// #         .into_iter()
+    /// #         .flatten()
+    /// #     }
+    /// # }
+    ///
+    /// fn nat(n: usize) -> Expr {
+    ///     (0..n).fold(Zero, |e, _| Succ(Box::new(e)))
+    /// }
+    ///
+    /// fn add_zero(expr: Expr) -> Rewrite<Expr> {
+    ///     match expr {
+    ///         Add(a, b) if *b == Zero => Dirty(*a),
+    ///         _ => Clean(expr),
+    ///     }
+    /// }
+    ///
+    /// let deep = || Succ(Box::new(Add(Box::new(nat(3)), Box::new(Zero))));
+    /// assert_eq!(
+    ///     deep().bottom_up(add_zero),
+    ///     deep().bottom_up_iterative_spine(add_zero),
+    /// );
+    /// assert_eq!(Dirty(nat(4)), deep().bottom_up_iterative_spine(add_zero));
+    ///
+    /// // Deep enough that plain recursion would likely overflow the stack
+    /// let long = nat(1_000_000);
+    /// let long = long.bottom_up_iterative_spine(add_zero);
+    /// assert!(long.is_clean());
+    /// # let mut long = long.into_inner();
+    /// # while let Succ(e) = long { long = *e; } // Avoid a recursive drop
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn bottom_up_iterative_spine(
+        self,
+        mut f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self>
+    where
+        Self: Children + Default,
+    {
+        fn go<S: TreeRewrite + Children + Default>(
+            mut branch: S,
+            f: &mut impl FnMut(S) -> Rewrite<S>,
+        ) -> Rewrite<S> {
+            let mut spine = Vec::new();
+            while branch.arity() == 1 {
+                let mut child = S::default();
+                spine.push(branch.each_branch(|only| {
+                    child = only;
+                    Clean(S::default())
+                }));
+                branch = child;
+            }
+            let mut transformed =
+                branch.each_branch(|branch| go(branch, f)).bind(&mut *f);
+            while let Some(parent) = spine.pop() {
+                let mut child = Some(transformed);
+                transformed = parent
+                    .bind(|parent| {
+                        parent.each_branch(|_| {
+                            child.take().unwrap_or_else(|| Clean(S::default()))
+                        })
+                    })
+                    .bind(&mut *f);
+            }
+            transformed
+        }
+        go(self, &mut f)
+    }
+
+    /// A version of [`bottom_up`] for rules that may return a larger node than
+    /// they were given, such as by wrapping it in a new parent.
+    ///
+    /// This behaves exactly like [`bottom_up`], and exists to spell out the
+    /// guarantee that makes such rules safe: the function is applied to each
+    /// node once, after its branches, and whatever it returns is never
+    /// traversed again in the same pass. A rule that always wraps its input
+    /// therefore terminates, adding one wrapper per original node. Use
+    /// [`normalize`] or similar to run further passes over the new nodes.
+    ///
+    /// [`bottom_up`]: TreeWalk::bottom_up
+    /// [`normalize`]: TreeRewrite::normalize
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trexp::{Rewrite, Clean, Dirty, TreeRewrite, TreeWalk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Stmt {
+    ///     Print(&'static str),
+    ///     Loop(Box<Stmt>),
+    ///     Block(Vec<Stmt>),
+    /// }
+    /// # use Stmt::*;
+    /// # impl TreeWalk<Rewrite<Self>> for Stmt {
+    /// #     fn each_branch(
+    /// #         self,
+    /// #         mut f: impl FnMut(Self) -> Rewrite<Self>,
+    /// #     ) -> Rewrite<Self> {
+    /// #         match self {
+    /// #             Print(_) => Clean(self),
+    /// #             Loop(body) => f(*body).map(|body| Loop(Box::new(body))),
+    /// #             Block(stmts) => stmts.into_iter().map(f).collect::<Rewrite<_>>().map(Block),
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// let mut visited = 0;
+    /// let tree = Loop(Box::new(Print("hi")));
+    /// let desugared = tree.bottom_up_wrapping(|stmt| {
+    ///     visited += 1;
+    ///     match stmt {
+    ///         Loop(body) => Dirty(Loop(Box::new(Block(vec![*body])))),
+    ///         _ => Clean(stmt),
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(Dirty(Loop(Box::new(Block(vec![Print("hi")])))), desugared);
+    /// // The new block and the print inside of it were not visited again
+    /// assert_eq!(2, visited);
+    /// ```
+    fn bottom_up_wrapping(
+        self,
+        f: impl FnMut(Self) -> Rewrite<Self>,
+    ) -> Rewrite<Self> {
+        self.bottom_up(f)
+    }
+}
+
+impl<T: TreeWalk<Rewrite<Self>>> TreeRewrite for T {}